@@ -3,14 +3,17 @@ use std::{
     fmt::Write,
     fs::{self, File},
     io::Write as _,
+    path::{Path, PathBuf},
     process::Command,
 };
-macro_rules! kwin {
-    () => {
-        env::var("KWIN_INCLUDE")
-            .as_deref()
-            .unwrap_or(
-                r#"
+#[allow(dead_code)]
+#[path = "src/dwarf.rs"]
+mod dwarf;
+#[allow(dead_code)]
+#[path = "src/elf.rs"]
+mod elf;
+/// the include directories used when nothing is discovered, the layout of Arch.
+const DEFAULT_INCLUDE: &str = r#"
                 /usr/include
                 /usr/include/kwin
                 /usr/include/KF6/KConfig
@@ -21,24 +24,235 @@ macro_rules! kwin {
                 /usr/include/qt6/QtDBus
                 /usr/include/qt6/QtGui
                 /usr/include/qt6/QtWidgets
-            "#,
-            )
-            .split('\n')
-            .map(str::trim)
-            .filter(|x| x.len() > 0)
+            "#;
+/// CMake packages providing the headers included by `workspace.h`.
+const CMAKE_PACKAGES: [&str; 7] = [
+    "KWin",
+    "KF6Config",
+    "KF6WindowSystem",
+    "Qt6Core",
+    "Qt6DBus",
+    "Qt6Gui",
+    "Qt6Widgets",
+];
+/// pkg-config modules of Qt, KWin and KF6 do not ship any.
+const PKG_CONFIG_MODULES: [&str; 4] = ["Qt6Core", "Qt6DBus", "Qt6Gui", "Qt6Widgets"];
+
+/// include directories of KWin, KF6 and Qt6, from `KWIN_INCLUDE` (one per line) if set, otherwise from pkg-config
+/// and the installed CMake config files, otherwise `DEFAULT_INCLUDE`.
+fn kwin_includes() -> Vec<String> {
+    if let Ok(x) = env::var("KWIN_INCLUDE") {
+        return lines(&x);
+    }
+    let mut ret = pkg_config_includes();
+    for package in CMAKE_PACKAGES {
+        if let Some(targets) = cmake_file(package, &format!("{package}Targets.cmake")) {
+            ret.extend(cmake_values(&targets, "INTERFACE_INCLUDE_DIRECTORIES"));
+            // `workspace.h` includes the other headers of kwin relative to the include root.
+            if package == "KWin" {
+                ret.push(format!("{}/include", cmake_prefix(&targets)));
+            }
+        }
+    }
+    if ret.is_empty() {
+        return lines(DEFAULT_INCLUDE);
+    }
+    let mut seen = std::collections::HashSet::new();
+    ret.retain(|x| seen.insert(x.clone()));
+    ret
+}
+/// the libkwin to read offsets from, `KWIN_LIBRARY` if set, otherwise from the CMake config of KWin, otherwise the
+/// first existing one of the usual locations.
+fn kwin_library() -> String {
+    if let Ok(x) = env::var("KWIN_LIBRARY") {
+        return x;
+    }
+    let dir = cmake_dirs("KWin").find(|x| x.is_dir());
+    // a per-config file might be added, the read ones are tracked by `cmake_values`.
+    if let Some(x) = &dir {
+        println!("cargo:rerun-if-changed={}", x.display());
+    }
+    let cmake = fs::read_dir(dir.unwrap_or_default())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|x| x.path())
+        .filter(|x| {
+            x.file_name()
+                .and_then(|x| x.to_str())
+                .is_some_and(|x| x.starts_with("KWinTargets-"))
+        })
+        .flat_map(|x| cmake_values(&x, "IMPORTED_LOCATION_"))
+        .find(|x| {
+            x.rsplit('/')
+                .next()
+                .is_some_and(|x| x.starts_with("libkwin.so"))
+        });
+    cmake
+        .into_iter()
+        .chain(
+            [
+                "/usr/lib/libkwin.so",
+                "/usr/lib64/libkwin.so",
+                &format!("/usr/lib/{}-linux-gnu/libkwin.so", target_arch()),
+            ]
+            .map(str::to_owned),
+        )
+        .find(|x| fs::exists(x).unwrap_or(false))
+        .unwrap_or_else(|| "/usr/lib/libkwin.so".to_owned())
+}
+fn lines(x: &str) -> Vec<String> {
+    x.split('\n')
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+fn target_arch() -> String {
+    env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_else(|_| "x86_64".to_owned())
+}
+/// `-I` flags of `PKG_CONFIG_MODULES`, nothing if pkg-config is missing or fails.
+///
+/// The `.pc` files read are tracked, as `read_tracked` does.
+fn pkg_config_includes() -> Vec<String> {
+    let pkg_config = env::var("PKG_CONFIG").unwrap_or_else(|_| "pkg-config".to_owned());
+    for module in PKG_CONFIG_MODULES {
+        if let Ok(x) = Command::new(&pkg_config).args(["--path", module]).output()
+            && x.status.success()
+        {
+            for path in String::from_utf8_lossy(&x.stdout).lines() {
+                println!("cargo:rerun-if-changed={}", path.trim());
+            }
+        }
+    }
+    match Command::new(pkg_config)
+        .arg("--cflags-only-I")
+        .args(PKG_CONFIG_MODULES)
+        .output()
+    {
+        Ok(x) if x.status.success() => String::from_utf8_lossy(&x.stdout)
+            .split_whitespace()
+            .filter_map(|x| x.strip_prefix("-I"))
+            .map(str::to_owned)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+/// candidate directories of the CMake package `name`, under `CMAKE_PREFIX_PATH` and the system prefixes.
+fn cmake_dirs(name: &str) -> impl Iterator<Item = PathBuf> {
+    let prefixes = env::var("CMAKE_PREFIX_PATH").unwrap_or_default();
+    let libdirs = [
+        "lib".to_owned(),
+        "lib64".to_owned(),
+        format!("lib/{}-linux-gnu", target_arch()),
+        "share".to_owned(),
+    ];
+    let name = name.to_owned();
+    prefixes
+        .split(':')
+        .filter(|x| !x.is_empty())
+        .map(str::to_owned)
+        .chain(["/usr".to_owned(), "/usr/local".to_owned()])
+        .collect::<Vec<_>>()
+        .into_iter()
+        .flat_map(move |prefix| {
+            let name = name.clone();
+            libdirs
+                .clone()
+                .into_iter()
+                .map(move |x| Path::new(&prefix).join(x).join("cmake").join(&name))
+        })
+}
+/// read `path`, and rerun the build script once it changes.
+fn read_tracked(path: &Path) -> std::io::Result<String> {
+    let ret = fs::read_to_string(path)?;
+    println!("cargo:rerun-if-changed={}", path.display());
+    Ok(ret)
+}
+fn cmake_file(name: &str, file: &str) -> Option<PathBuf> {
+    cmake_dirs(name).map(|x| x.join(file)).find(|x| x.is_file())
+}
+/// `_IMPORT_PREFIX` of an exported targets file: its directory, up once per `get_filename_component` on itself.
+fn cmake_prefix(targets: &Path) -> String {
+    let text = read_tracked(targets).unwrap_or_default();
+    let up = text
+        .matches(r#"get_filename_component(_IMPORT_PREFIX "${_IMPORT_PREFIX}" PATH)"#)
+        .count();
+    let mut prefix = targets.parent().unwrap_or(targets);
+    for _ in 0..up {
+        prefix = prefix.parent().unwrap_or(prefix);
+    }
+    prefix.display().to_string()
+}
+/// values of properties starting with `property` in a targets file, generator expressions are skipped.
+fn cmake_values(targets: &Path, property: &str) -> Vec<String> {
+    let text = match read_tracked(targets) {
+        Ok(x) => x,
+        Err(_) => return Vec::new(),
     };
+    // the per-config files are in the same directory as the main one.
+    let main = targets.with_file_name(
+        targets
+            .file_name()
+            .and_then(|x| x.to_str())
+            .and_then(|x| Some(format!("{}Targets.cmake", x.split_once("Targets")?.0)))
+            .unwrap_or_default(),
+    );
+    let prefix = cmake_prefix(&main);
+    text.lines()
+        .map(str::trim)
+        .filter(|x| x.starts_with(property))
+        .filter_map(|x| Some(x.split_once('"')?.1.rsplit_once('"')?.0))
+        .flat_map(|x| x.split(';'))
+        .map(|x| x.replace("${_IMPORT_PREFIX}", &prefix))
+        .filter(|x| !x.is_empty() && !x.contains("$<") && !x.contains("${"))
+        .collect()
 }
-// fn kwin<'a, E>(var: Result<&'a str, E>) -> impl Iterator<Item = &'a str> {
-//     var
-// }
 fn main() {
-    // The bindgen::Builder is the main entry point
-    // to bindgen, and lets you build up options for
-    // the resulting bindings.
-    if cfg!(feature = "docgen-detect") && !kwin!().all(|x| fs::exists(x).unwrap_or(false)) {
-        #[cfg(not(doc))]
+    for var in [
+        "KWIN_INCLUDE",
+        "KWIN_LIBRARY",
+        "CMAKE_PREFIX_PATH",
+        "PKG_CONFIG",
+        "PKG_CONFIG_PATH",
+        "DOCS_RS",
+        "KWIN_MOUSE_LOC_DUMMY",
+    ] {
+        println!("cargo:rerun-if-env-changed={var}");
+    }
+    let library = kwin_library();
+    // the offsets and build-id are read from libkwin, an upgrade replaces the file (or the one it links to).
+    if let Ok(real) = fs::canonicalize(&library) {
+        println!("cargo:rerun-if-changed={library}");
+        if real != Path::new(&library) {
+            println!("cargo:rerun-if-changed={}", real.display());
+        }
+    }
+    let includes = kwin_includes();
+    let missing = includes
+        .iter()
+        .filter(|x| !fs::exists(x).unwrap_or(false))
+        .collect::<Vec<_>>();
+    // the debug info of libkwin.so tells the exact offset without any header, bindgen is the fallback.
+    let mut info = Info {
+        library: &library,
+        includes: &includes,
+        source: "Dwarf",
+        workspace_size: dwarf::workspace_size(&library).ok(),
+    };
+    if let Ok(pos) = dwarf::focus_mouse_pos_offset(&library) {
+        write_consts(&pos.to_string(), &info);
+    } else if cfg!(feature = "docgen-detect") && !missing.is_empty() {
+        // dummy offsets silently break every user, they are only acceptable for documents.
+        if env::var_os("DOCS_RS").is_none() && env::var_os("KWIN_MOUSE_LOC_DUMMY").is_none() {
+            panic!(
+                "the headers of KWin are not found ({missing:?} does not exist), and {library} has no debug info. \
+                 Install the development files of kwin, or set KWIN_INCLUDE (one directory per line) and KWIN_LIBRARY. \
+                 Set KWIN_MOUSE_LOC_DUMMY=1 to generate dummy offsets for documents."
+            );
+        }
         println!(
-            "cargo:warning=Some folder located in KWIN_INCLUDE (or the default files) does not exists, generating dummy offset file instead. Please set KWIN_INCLUDE manually to prevent this warning occurs."
+            "cargo:warning=Some folder located in KWIN_INCLUDE (or the discovered ones) does not exists, generating dummy offset file for documents."
         );
         let mut file = File::create(format!("{}/consts.rs", env::var("OUT_DIR").unwrap())).unwrap();
         write!(
@@ -51,56 +265,68 @@ pub(crate) static mut POS_OFFSET: usize = 176usize; // seems stable
 #[used]
 #[unsafe(link_section = ".kwin.mouse.loc.kwin")]
 pub(crate) static mut WORKSPACE_OFFSET: usize = 0x0usize;
-"#
+{}"#,
+            Info {
+                source: "Dummy",
+                ..info
+            }
+            .render("176usize", 0)
         )
         .unwrap();
         return;
-    }
-    let bindings = bindgen::Builder::default()
-        // The input header we would like to generate
-        // bindings for.
-        .use_core()
-        .header_contents("header.hpp", "#include<workspace.h>")
-        .allowlist_type("^(.*Workspace.*)$")
-        .clang_args(
-            kwin!()
-                .map(|x| format!("-I{}", x))
-                .chain(
-                    env::var("CUSTOM_ARGS")
-                        .as_deref()
-                        .unwrap_or("")
-                        .split(' ')
-                        .filter(|x| x.len() > 0)
-                        .map(|x| x.to_owned()),
-                )
-                .chain(["-x", "c++", "-std=c++20"].map(ToString::to_string)),
-        )
-        // Tell cargo to invalidate the built crate whenever any of the
-        // included header files changed.
-        .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
-        // Finish the builder and generate the bindings.
-        .generate()
-        // Unwrap the Result and panic on failure.
-        .expect("Unable to generate bindings");
+    } else {
+        // The bindgen::Builder is the main entry point
+        // to bindgen, and lets you build up options for
+        // the resulting bindings.
+        let bindings = bindgen::Builder::default()
+            // The input header we would like to generate
+            // bindings for.
+            .use_core()
+            .header_contents("header.hpp", "#include<workspace.h>")
+            .allowlist_type("^(.*Workspace.*)$")
+            .clang_args(
+                includes
+                    .iter()
+                    .map(|x| format!("-I{}", x))
+                    .chain(
+                        env::var("CUSTOM_ARGS")
+                            .as_deref()
+                            .unwrap_or("")
+                            .split(' ')
+                            .filter(|x| x.len() > 0)
+                            .map(|x| x.to_owned()),
+                    )
+                    .chain(["-x", "c++", "-std=c++20"].map(ToString::to_string)),
+            )
+            // Tell cargo to invalidate the built crate whenever any of the
+            // included header files changed.
+            .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+            // Finish the builder and generate the bindings.
+            .generate()
+            // Unwrap the Result and panic on failure.
+            .expect("Unable to generate bindings");
 
-    // Write the bindings to the OUT_DIR file.
-    let mut file = File::create(format!("{}/consts.rs", env::var("OUT_DIR").unwrap()))
-        .expect("cannot save to $OUT_DIR");
-    write!(&mut file, "#[used]\n#[unsafe(link_section = \".kwin.mouse.loc.pos\")]\npub(crate) static mut POS_OFFSET: usize = {};\n#[used]\n#[unsafe(link_section = \".kwin.mouse.loc.kwin\")]\npub(crate) static mut WORKSPACE_OFFSET: usize = 0x{:06x}usize;\n",
-        bindings
-            .to_string()
-            .split_once(r#"offset_of!(KWin_Workspace, focusMousePos)"#)
-            .expect("cannot calculate offset of focusMousePos.")
-            .1
-            .split_once("]")
-            .expect(r#"do not find line [::core::mem::offset_of!(KWin_Workspace, focusMousePos) - $(SIZE)]."#)
-            .0
-            .split_once("-")
-            .expect("grab offset failed")
-            .1
-            .trim(),
-        usize::from_str_radix(String::from_utf8(Command::new("readelf").args(["-WCs", "/usr/lib/libkwin.so"]).output().expect("readelf execute failed").stdout).expect("failed to parse readelf").split_once(r#"KWin::Workspace::_self"#).expect("cannot find KWin::Workspace::_self").0.rsplit_once('\n').expect("cannot read offset of KWin::Workspace::_self").1.split_once(':').expect("parse `:` failed.").1.trim().split_once(' ').expect("cannot parse space").0,16).expect("cannot parse offset")
-    ).expect("write failed");
+        let bindings = bindings.to_string();
+        info.source = "Bindgen";
+        info.workspace_size = bindings
+            .split_once("size_of::<KWin_Workspace>() - ")
+            .and_then(|x| x.1.split_once("usize]"))
+            .and_then(|x| x.0.trim().parse().ok());
+        write_consts(
+            bindings
+                .split_once(r#"offset_of!(KWin_Workspace, focusMousePos)"#)
+                .expect("cannot calculate offset of focusMousePos.")
+                .1
+                .split_once("]")
+                .expect(r#"do not find line [::core::mem::offset_of!(KWin_Workspace, focusMousePos) - $(SIZE)]."#)
+                .0
+                .split_once("-")
+                .expect("grab offset failed")
+                .1
+                .trim(),
+            &info,
+        );
+    }
 
     if cfg!(feature = "uinput") {
         let contents = &mut "#include<linux/input.h>\n#include<linux/uinput.h>\n".to_owned();
@@ -114,13 +340,17 @@ pub(crate) static mut WORKSPACE_OFFSET: usize = 0x0usize;
         force_include(contents, "unsigned long", "UI_SET_KEYBIT");
         force_include(contents, "unsigned long", "UI_SET_RELBIT");
         force_include(contents, "unsigned long", "UI_SET_ABSBIT");
+        force_include(contents, "unsigned long", "UI_ABS_SETUP");
+        force_include(contents, "unsigned long", "UI_SET_MSCBIT");
+        // a function-like macro, the length of the buffer used by `IoCtl::sysname` is fixed.
+        contents.push_str("\nconst unsigned long UI_GET_SYSNAME_64 = UI_GET_SYSNAME(64);");
 
         let bindings = bindgen::Builder::default()
             // The input header we would like to generate
             // bindings for.
             .use_core()
             .header_contents("header.h", contents)
-            .allowlist_type("^((input_event|uinput_setup))$")
+            .allowlist_type("^((input_event|uinput_setup|uinput_abs_setup))$")
             .allowlist_var("^(.*)$")
             .blocklist_var("^(rust_bindgen_exclude_.*)$")
             .default_visibility(bindgen::FieldVisibilityKind::Public)
@@ -145,3 +375,55 @@ pub(crate) static mut WORKSPACE_OFFSET: usize = 0x0usize;
             .expect("cannot deal with uinput")
     }
 }
+/// what `BUILD_INFO` records besides the offsets.
+#[derive(Clone, Copy)]
+struct Info<'a> {
+    library: &'a str,
+    includes: &'a [String],
+    /// variant of `consts::OffsetSource`.
+    source: &'a str,
+    workspace_size: Option<usize>,
+}
+impl Info<'_> {
+    /// build-id of the library in lowercase hex.
+    fn build_id(&self) -> Option<String> {
+        elf::Elf::open(self.library).ok().and_then(|x| {
+            Some(
+                x.build_id()?
+                    .iter()
+                    .map(|x| format!("{x:02x}"))
+                    .collect::<String>(),
+            )
+        })
+    }
+    /// the `BUILD_INFO` item of `consts.rs`.
+    fn render(&self, pos: &str, workspace: usize) -> String {
+        let build_id = self.build_id();
+        let version = fs::canonicalize(self.library)
+            .ok()
+            .and_then(|x| Some(x.file_name()?.to_str()?.split_once(".so.")?.1.to_owned()));
+        format!(
+            "/// what the build script knew about libkwin.\npub const BUILD_INFO: BuildInfo = BuildInfo {{ library: {:?}, version: {version:?}, build_id: {build_id:?}, source: OffsetSource::{}, includes: &{:?}, workspace_size: {:?}, workspace_offset: 0x{workspace:06x}usize, pos_offset: {pos} }};\n",
+            self.library, self.source, self.includes, self.workspace_size,
+        )
+    }
+}
+/// write `consts.rs` with the offset of `focusMousePos` (`pos`), the offset of `KWin::Workspace::_self` read from `info.library`, and `BUILD_INFO`.
+fn write_consts(pos: &str, info: &Info) {
+    // Write the bindings to the OUT_DIR file.
+    let mut file = File::create(format!("{}/consts.rs", env::var("OUT_DIR").unwrap()))
+        .expect("cannot save to $OUT_DIR");
+    let workspace =
+        elf::workspace_offset(info.library).expect("cannot read offset of KWin::Workspace::_self");
+    write!(&mut file, "#[used]\n#[unsafe(link_section = \".kwin.mouse.loc.pos\")]\npub(crate) static mut POS_OFFSET: usize = {};\n#[used]\n#[unsafe(link_section = \".kwin.mouse.loc.kwin\")]\npub(crate) static mut WORKSPACE_OFFSET: usize = 0x{:06x}usize;\n{}",
+        pos,
+        workspace,
+        info.render(pos, workspace)
+    ).expect("write failed");
+    if info.build_id().is_none() {
+        println!(
+            "cargo:warning={} has no build-id, `Workspace::get` cannot tell whether the offsets still apply.",
+            info.library
+        );
+    }
+}
@@ -0,0 +1,146 @@
+// included in `consts`.
+
+/// options of `calibrate_pos_with`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CalibrateOptions {
+    window: usize,
+    step: i32,
+    settle: std::time::Duration,
+}
+impl Default for CalibrateOptions {
+    fn default() -> Self {
+        Self {
+            window: 0x800,
+            step: 40,
+            settle: std::time::Duration::from_millis(50),
+        }
+    }
+}
+impl CalibrateOptions {
+    /// scan 0x800 bytes, move 40 pixels per step, wait 50ms after each move.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// bytes of the `KWin::Workspace` object to scan, from its start.
+    pub fn window(mut self, bytes: usize) -> Self {
+        self.window = bytes;
+        self
+    }
+    /// relative movement of each step.
+    pub fn step(mut self, pixels: i32) -> Self {
+        self.step = pixels;
+        self
+    }
+    /// time to wait for kwin to process each movement.
+    pub fn settle(mut self, settle: std::time::Duration) -> Self {
+        self.settle = settle;
+        self
+    }
+}
+
+/// result of `calibrate_pos`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Calibration {
+    /// the discovered offset of `focusMousePos` in `KWin::Workspace`.
+    pub offset: usize,
+    /// a score in `0.0..=1.0`, the fraction of checks passed divided by the number of equally good offsets.
+    pub confidence: f64,
+    /// every offset with the best score, including `offset`.
+    pub ties: Vec<usize>,
+}
+
+/// find the offset of `focusMousePos` at runtime, without headers or bindgen.
+///
+/// The cursor is pushed to the top-left corner, moved along a fixed path with `device`, and pushed back to the corner.
+/// The pair of f64 that follows the direction of every move, and returns to the same value at the corner, wins.
+/// Pointer acceleration is harmless since only the directions are compared. The cursor is left at the corner.
+///
+/// ```no_run
+/// use kwin_mouse_loc::{consts::calibrate_pos, device::IoCtl, pointer::Workspace};
+/// let workspace = unsafe { Workspace::new(true) };
+/// let calibration = calibrate_pos(&workspace, &mut IoCtl::new()).unwrap();
+/// println!("focusMousePos is at 0x{:x} ({:.2})", calibration.offset, calibration.confidence);
+/// ```
+pub fn calibrate_pos(
+    workspace: &crate::pointer::Workspace,
+    device: &mut crate::device::IoCtl,
+) -> Result<Calibration, crate::pointer::Error> {
+    calibrate_pos_with(workspace, device, CalibrateOptions::default())
+}
+
+/// like `calibrate_pos`, with the scanned window, step and settle time.
+pub fn calibrate_pos_with(
+    workspace: &crate::pointer::Workspace,
+    device: &mut crate::device::IoCtl,
+    options: CalibrateOptions,
+) -> Result<Calibration, crate::pointer::Error> {
+    use crate::pointer::Snapshot;
+    let object = workspace.try_object()?;
+    // one field per f64 aligned offset, fields that cannot be read are never chosen.
+    let mut snapshot = Snapshot::new(workspace.pid());
+    let fields = (0..=options.window.saturating_sub(16))
+        .step_by(8)
+        .map(|offset| snapshot.field::<(f64, f64)>(unsafe { object.byte_add(offset) }))
+        .collect::<Vec<_>>();
+    let s = options.step;
+    // starting from the corner, the cursor never leaves the screen, thus is never clamped.
+    let moves = [(s, 0), (0, s), (s, s), (-s, 0), (0, -s), (-s, -s)];
+    let mut read = || -> Result<Vec<Option<(f64, f64)>>, crate::pointer::Error> {
+        std::thread::sleep(options.settle);
+        let reading = snapshot.read()?;
+        Ok(fields.iter().map(|x| reading.get(*x).ok()).collect())
+    };
+    // larger than any screen, libinput clamps the cursor at the edge.
+    let corner = -0x8000;
+    device.move_mouse(corner, corner);
+    let mut readings = vec![read()?];
+    for (dx, dy) in moves {
+        device.move_mouse(dx, dy);
+        readings.push(read()?);
+    }
+    device.move_mouse(corner, corner);
+    readings.push(read()?);
+
+    Ok(score(&moves, &readings))
+}
+
+/// pick the offsets from `readings`, taken at the corner, after each of `moves` and at the corner again.
+///
+/// `readings[k][i]` is the pair at offset `8 * i` in the k-th reading, `None` if it cannot be read.
+pub(crate) fn score(moves: &[(i32, i32)], readings: &[Vec<Option<(f64, f64)>>]) -> Calibration {
+    let sign = |x: f64| if x == 0.0 { 0 } else { x.signum() as i32 };
+    let fields = readings.first().map_or(0, |x| x.len());
+    let scores = (0..fields)
+        .map(|i| {
+            let Some(pos) = readings.iter().map(|x| x[i]).collect::<Option<Vec<_>>>() else {
+                return 0;
+            };
+            if pos.iter().any(|x| !x.0.is_finite() || !x.1.is_finite()) {
+                return 0;
+            }
+            let tracked = moves
+                .iter()
+                .zip(pos.windows(2))
+                .filter(|((dx, dy), p)| {
+                    sign(p[1].0 - p[0].0) == dx.signum() && sign(p[1].1 - p[0].1) == dy.signum()
+                })
+                .count();
+            tracked + (pos[0] == pos[pos.len() - 1]) as usize
+        })
+        .collect::<Vec<_>>();
+    let best = scores.iter().copied().max().unwrap_or(0);
+    let ties = scores
+        .iter()
+        .enumerate()
+        .filter(|x| best > 0 && *x.1 == best)
+        .map(|x| x.0 * 8)
+        .collect::<Vec<_>>();
+    Calibration {
+        offset: ties.first().copied().unwrap_or(0),
+        confidence: match ties.len() {
+            0 => 0.0,
+            n => best as f64 / (moves.len() + 1) as f64 / n as f64,
+        },
+        ties,
+    }
+}
@@ -0,0 +1,168 @@
+// included in `pointer`.
+
+/// options of `Controller`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MoveOptions {
+    tolerance: f64,
+    timeout: std::time::Duration,
+    settle: std::time::Duration,
+    max_step: f64,
+}
+impl Default for MoveOptions {
+    fn default() -> Self {
+        Self {
+            tolerance: 1.0,
+            timeout: std::time::Duration::from_secs(1),
+            settle: std::time::Duration::from_millis(10),
+            max_step: 400.0,
+        }
+    }
+}
+impl MoveOptions {
+    /// within 1 pixel in 1 second, wait 10ms after each move, at most 400 device units per move.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// the largest distance (in logical pixels) to the target considered as arrived.
+    pub fn tolerance(mut self, pixels: f64) -> Self {
+        self.tolerance = pixels;
+        self
+    }
+    /// time budget of a single `move_to`.
+    pub fn timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    /// time to wait for kwin to process each movement.
+    pub fn settle(mut self, settle: std::time::Duration) -> Self {
+        self.settle = settle;
+        self
+    }
+    /// the largest relative movement sent at once, in device units.
+    pub fn max_step(mut self, units: f64) -> Self {
+        self.max_step = units;
+        self
+    }
+}
+
+/// result of `Controller::move_to`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Arrival {
+    /// the last position read.
+    pub pos: (f64, f64),
+    /// distance from `pos` to the target.
+    pub error: f64,
+    /// number of movements sent.
+    pub iterations: usize,
+    /// whether `error` is within the tolerance, otherwise the time budget is exhausted.
+    pub converged: bool,
+}
+
+/// moves the cursor to exact positions with relative movements, correcting the pointer acceleration by reading
+/// the true position of `mouse` after each move.
+///
+/// The acceleration is learned as the ratio of pixels moved per device unit, for each power of 2 of the movement
+/// length, and kept for later moves.
+/// ```no_run
+/// use kwin_mouse_loc::{device::IoCtl, pointer::{Controller, Workspace}};
+/// let mouse = unsafe { Workspace::new(true).get_mouse() };
+/// let mut device = IoCtl::new();
+/// let mut controller = Controller::new(&mouse, &mut device);
+/// let arrival = controller.move_to(800.0, 600.0).unwrap();
+/// println!("{:?} after {} moves, error {:.2}", arrival.pos, arrival.iterations, arrival.error);
+/// ```
+pub struct Controller<'a> {
+    mouse: &'a Mouse,
+    device: &'a mut crate::device::IoCtl,
+    options: MoveOptions,
+    /// learned pixels per unit, indexed by the base-2 logarithm of the movement length in units.
+    gains: [Option<f64>; 16],
+}
+impl<'a> Controller<'a> {
+    /// a controller with default options and nothing learned.
+    pub fn new(mouse: &'a Mouse, device: &'a mut crate::device::IoCtl) -> Self {
+        Self {
+            mouse,
+            device,
+            options: MoveOptions::default(),
+            gains: [None; 16],
+        }
+    }
+    /// replace the options.
+    pub fn options(mut self, options: MoveOptions) -> Self {
+        self.options = options;
+        self
+    }
+    /// the learned acceleration curve, as (movement length in units, pixels per unit) for each learned bucket.
+    pub fn curve(&self) -> Vec<(f64, f64)> {
+        self.gains
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| Some(((1u32 << i) as f64, (*x)?)))
+            .collect()
+    }
+    /// move the cursor to the logical position `(x, y)`, until it is within the tolerance or the time is out.
+    pub fn move_to(&mut self, x: f64, y: f64) -> Result<Arrival, Error> {
+        let start = std::time::Instant::now();
+        let mut pos = self.mouse.try_loc()?;
+        let mut iterations = 0;
+        loop {
+            let error = (x - pos.0).hypot(y - pos.1);
+            let converged = error <= self.options.tolerance;
+            if converged || start.elapsed() >= self.options.timeout {
+                return Ok(Arrival {
+                    pos,
+                    error,
+                    iterations,
+                    converged,
+                });
+            }
+            // the gain depends on the length, which depends on the gain, two rounds are close enough.
+            let mut units = error;
+            for _ in 0..2 {
+                units = (error / gain(&self.gains, units)).min(self.options.max_step);
+            }
+            let k = units / error;
+            let (mut dx, mut dy) = (((x - pos.0) * k).round(), ((y - pos.1) * k).round());
+            if dx == 0.0 && dy == 0.0 {
+                // sub-unit corrections still move along the larger axis.
+                if (x - pos.0).abs() >= (y - pos.1).abs() {
+                    dx = (x - pos.0).signum();
+                } else {
+                    dy = (y - pos.1).signum();
+                }
+            }
+            self.device.move_mouse(dx as i32, dy as i32);
+            std::thread::sleep(self.options.settle);
+            iterations += 1;
+            let last = std::mem::replace(&mut pos, self.mouse.try_loc()?);
+            if let Some(x) = learn((dx, dy), (pos.0 - last.0, pos.1 - last.1)) {
+                let gain = &mut self.gains[bucket(dx.hypot(dy))];
+                *gain = Some(gain.map_or(x, |g| g * 0.5 + x * 0.5));
+            }
+        }
+    }
+}
+/// index in `Controller::gains` of a movement of `units`.
+pub(crate) fn bucket(units: f64) -> usize {
+    (units.max(1.0).log2() as usize).min(15)
+}
+/// pixels per unit of a movement of `units`, from the nearest learned bucket, 1 if nothing is learned.
+pub(crate) fn gain(gains: &[Option<f64>; 16], units: f64) -> f64 {
+    let i = bucket(units);
+    (0..16)
+        .filter_map(|j| Some((i.abs_diff(j), gains[j]?)))
+        .min_by_key(|x| x.0)
+        .map_or(1.0, |x| x.1)
+}
+/// pixels per unit of the movement `sent` (in units) which moved the cursor by `moved` (in pixels), `None` if it
+/// tells nothing about the acceleration.
+///
+/// The acceleration scales both axes alike, thus an axis clamped at the edges of the outputs (or a cursor moved by
+/// someone else) shows as an axis disagreeing with the overall ratio.
+pub(crate) fn learn(sent: (f64, f64), moved: (f64, f64)) -> Option<f64> {
+    let length = moved.0.hypot(moved.1);
+    let ratio = length / sent.0.hypot(sent.1);
+    let agree = |sent: f64, moved: f64| (moved - sent * ratio).abs() <= length * 0.1;
+    (length > 0.0 && agree(sent.0, moved.0) && agree(sent.1, moved.1)).then_some(ratio)
+}
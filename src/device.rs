@@ -168,6 +168,7 @@ mod bindgen {
     }
 }
 
+use crate::pointer::Rect;
 use libc::ioctl;
 use std::{
     fs::{File, OpenOptions},
@@ -176,7 +177,19 @@ use std::{
     time::Duration,
 };
 
-pub struct IoCtl(File, timeval, Vec<u8>);
+/// the hi-res units of one wheel detent.
+pub const WHEEL_DETENT: i32 = 120;
+
+/// add `value` hi-res units to `remainder`, take the whole detents out of it and return them.
+pub(crate) fn whole_detents(remainder: &mut i32, value: i32) -> i32 {
+    *remainder = remainder.saturating_add(value);
+    let detents = *remainder / WHEEL_DETENT;
+    *remainder -= detents * WHEEL_DETENT;
+    detents
+}
+
+/// a virtual mouse, with the remainders of hi-res scrolling (vertical, horizontal) not reported as detents yet.
+pub struct IoCtl(File, timeval, Vec<u8>, (i32, i32));
 impl Drop for IoCtl {
     fn drop(&mut self) {
         unsafe {
@@ -186,53 +199,34 @@ impl Drop for IoCtl {
 }
 impl IoCtl {
     pub fn new() -> Self {
-        let file = OpenOptions::new()
-            .write(true)
-            // .custom_flags(libc::O_NONBLOCK)
-            .open("/dev/uinput")
-            .unwrap();
-        let mut name = [0; 80];
-        name.iter_mut()
-            .zip("my-virtual-mouse".bytes().map(|x| x as i8))
-            .for_each(|(n, s)| *n = s);
-        let definition = uinput_setup {
-            id: input_id {
-                bustype: BUS_VIRTUAL as u16,
-                vendor: 0x045e,
-                product: 0x07a5,
-                version: 0x0111,
-            },
-            name,
-            ff_effects_max: 0,
-        };
-        let fd = file.as_raw_fd();
-        unsafe {
-            for code in [BTN_LEFT, BTN_RIGHT, BTN_MIDDLE] {
-                ioctl(fd, UI_SET_EVBIT, EV_KEY);
-                ioctl(fd, UI_SET_KEYBIT, code);
-            }
-
-            ioctl(fd, UI_SET_EVBIT, EV_REL);
-            ioctl(fd, UI_SET_RELBIT, REL_X);
-            ioctl(fd, UI_SET_RELBIT, REL_Y);
-            // no need to register ABSBIT since it is touchpad related things.
-            #[cfg(feature = "keyboard")]
-            for code in KEY_RESERVED..=KEY_MICMUTE {
-                ioctl(fd, UI_SET_EVBIT, EV_KEY);
-                ioctl(fd, UI_SET_KEYBIT, code);
-            }
-
-            ioctl(fd, UI_DEV_SETUP, &definition);
-            ioctl(fd, UI_DEV_CREATE);
+        let builder = DeviceBuilder::mouse();
+        #[cfg(feature = "keyboard")]
+        let builder = builder.keyboard();
+        builder.build().unwrap()
+    }
+    /// the name of the device in sysfs (e.g., `input42`), by `UI_GET_SYSNAME`.
+    pub fn sysname(&self) -> std::io::Result<String> {
+        let mut buf = [0u8; 64];
+        if unsafe { ioctl(self.0.as_raw_fd(), UI_GET_SYSNAME_64, buf.as_mut_ptr()) } < 0 {
+            return Err(std::io::Error::last_os_error());
         }
-        Self(
-            file,
-            timeval {
-                tv_sec: 0,
-                tv_usec: 0,
-            },
-            Vec::with_capacity(100),
-        )
+        let len = buf.iter().position(|x| *x == 0).unwrap_or(buf.len());
+        Ok(String::from_utf8_lossy(&buf[..len]).into_owned())
+    }
+    /// the event node (e.g., `/dev/input/event7`) of the device, found in `/sys/devices/virtual/input/{sysname}`.
+    pub fn devnode(&self) -> std::io::Result<std::path::PathBuf> {
+        let dir = std::path::Path::new("/sys/devices/virtual/input").join(self.sysname()?);
+        std::fs::read_dir(&dir)?
+            .flatten()
+            .map(|x| x.file_name())
+            .find(|x| x.to_string_lossy().starts_with("event"))
+            .map(|x| std::path::Path::new("/dev/input").join(x))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("no event node in {}", dir.display()),
+                )
+            })
     }
     fn event(&mut self, type_: u16, code: u16, value: i32) {
         use std::io::Write;
@@ -256,6 +250,32 @@ impl IoCtl {
         self.event(EV_REL as u16, REL_X as u16, x);
         self.send(EV_REL as u16, REL_Y as u16, y);
     }
+    /// scroll by detents (notches of the wheel), positive `vertical` scrolls up and positive `horizontal` scrolls right.
+    pub fn scroll(&mut self, vertical: i32, horizontal: i32) {
+        self.scroll_hi_res(
+            vertical.saturating_mul(WHEEL_DETENT),
+            horizontal.saturating_mul(WHEEL_DETENT),
+        )
+    }
+    /// smooth scrolling in 1/120 of a detent, the legacy wheel events are sent once a whole detent is accumulated.
+    pub fn scroll_hi_res(&mut self, vertical: i32, horizontal: i32) {
+        let axes = [
+            (vertical, REL_WHEEL, REL_WHEEL_HI_RES),
+            (horizontal, REL_HWHEEL, REL_HWHEEL_HI_RES),
+        ];
+        for (i, (value, detent, hi_res)) in axes.into_iter().enumerate() {
+            if value == 0 {
+                continue;
+            }
+            let remainder = if i == 0 { &mut self.3.0 } else { &mut self.3.1 };
+            let detents = whole_detents(remainder, value);
+            self.event(EV_REL as u16, hi_res as u16, value);
+            if detents != 0 {
+                self.event(EV_REL as u16, detent as u16, detents);
+            }
+        }
+        self.sync();
+    }
     pub fn press(&mut self, btn: impl IntoU16) {
         self.send(EV_KEY as u16, btn.into(), 1)
     }
@@ -281,6 +301,411 @@ impl IoCtl {
         }
     }
 }
+/// identity and capabilities of a virtual device, created by `build`.
+///
+/// ```no_run
+/// use kwin_mouse_loc::device::{BUS_USB, DeviceBuilder, KEY_A, KEY_B};
+/// let device = DeviceBuilder::mouse()
+///     .name("automation-mouse")
+///     .bus(BUS_USB as u16)
+///     .ids(0x1234, 0x5678, 1)
+///     .keys([KEY_A, KEY_B])
+///     .build()
+///     .unwrap();
+/// println!("created {}", device.devnode().unwrap().display());
+/// ```
+#[derive(Clone, Debug)]
+pub struct DeviceBuilder {
+    name: String,
+    id: input_id,
+    keys: Vec<u32>,
+    rels: Vec<u32>,
+    abs: Vec<(u32, input_absinfo)>,
+    mscs: Vec<u32>,
+}
+impl Default for DeviceBuilder {
+    fn default() -> Self {
+        Self {
+            name: "my-virtual-mouse".to_owned(),
+            id: input_id {
+                bustype: BUS_VIRTUAL as u16,
+                vendor: 0x045e,
+                product: 0x07a5,
+                version: 0x0111,
+            },
+            keys: Vec::new(),
+            rels: Vec::new(),
+            abs: Vec::new(),
+            mscs: Vec::new(),
+        }
+    }
+}
+impl DeviceBuilder {
+    /// a device named "my-virtual-mouse" on `BUS_VIRTUAL`, without any capability.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// the capabilities of `IoCtl::new` without the `keyboard` feature: buttons, relative motion and wheels.
+    pub fn mouse() -> Self {
+        Self::new()
+            .keys([BTN_LEFT, BTN_RIGHT, BTN_MIDDLE])
+            .rel(REL_X)
+            .rel(REL_Y)
+            .wheel()
+    }
+    /// the name of the device, at most 79 bytes are kept.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+    /// vendor, product and version ids.
+    pub fn ids(mut self, vendor: u16, product: u16, version: u16) -> Self {
+        self.id.vendor = vendor;
+        self.id.product = product;
+        self.id.version = version;
+        self
+    }
+    /// the bus type, e.g., `BUS_USB`.
+    pub fn bus(mut self, bus: u16) -> Self {
+        self.id.bustype = bus;
+        self
+    }
+    /// enable a key or button.
+    pub fn key(mut self, code: u32) -> Self {
+        self.keys.push(code);
+        self
+    }
+    /// enable keys or buttons.
+    pub fn keys(mut self, codes: impl IntoIterator<Item = u32>) -> Self {
+        self.keys.extend(codes);
+        self
+    }
+    /// enable every key from `KEY_RESERVED` to `KEY_MICMUTE`, as the `keyboard` feature does.
+    pub fn keyboard(self) -> Self {
+        self.keys(KEY_RESERVED..=KEY_MICMUTE)
+    }
+    /// enable a relative axis.
+    pub fn rel(mut self, code: u32) -> Self {
+        self.rels.push(code);
+        self
+    }
+    /// enable the wheels, both the legacy and hi-res axes.
+    pub fn wheel(self) -> Self {
+        [REL_WHEEL, REL_HWHEEL, REL_WHEEL_HI_RES, REL_HWHEEL_HI_RES]
+            .into_iter()
+            .fold(self, Self::rel)
+    }
+    /// enable an absolute axis ranging in `minimum..=maximum`, `resolution` in units per mm (0 if unknown).
+    pub fn abs(mut self, code: u32, minimum: i32, maximum: i32, resolution: i32) -> Self {
+        self.abs.push((
+            code,
+            input_absinfo {
+                value: minimum,
+                minimum,
+                maximum,
+                fuzz: 0,
+                flat: 0,
+                resolution,
+            },
+        ));
+        self
+    }
+    /// enable a miscellaneous event, e.g., `MSC_SCAN`.
+    pub fn msc(mut self, code: u32) -> Self {
+        self.mscs.push(code);
+        self
+    }
+    /// open `/dev/uinput`, register the capabilities and create the device.
+    pub fn build(&self) -> std::io::Result<IoCtl> {
+        let file = OpenOptions::new()
+            .write(true)
+            // .custom_flags(libc::O_NONBLOCK)
+            .open("/dev/uinput")?;
+        let fd = file.as_raw_fd();
+        let check = |ret: i32| {
+            if ret < 0 {
+                Err(std::io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        };
+        let mut name = [0; 80];
+        name.iter_mut()
+            .zip(self.name.bytes().take(79).map(|x| x as i8))
+            .for_each(|(n, s)| *n = s);
+        let definition = uinput_setup {
+            id: self.id,
+            name,
+            ff_effects_max: 0,
+        };
+        unsafe {
+            for (ev, bit, codes) in [
+                (EV_KEY, UI_SET_KEYBIT, &self.keys),
+                (EV_REL, UI_SET_RELBIT, &self.rels),
+                (EV_MSC, UI_SET_MSCBIT, &self.mscs),
+            ] {
+                if !codes.is_empty() {
+                    check(ioctl(fd, UI_SET_EVBIT, ev))?;
+                }
+                for code in codes {
+                    check(ioctl(fd, bit, *code))?;
+                }
+            }
+            if !self.abs.is_empty() {
+                check(ioctl(fd, UI_SET_EVBIT, EV_ABS))?;
+            }
+            for (code, absinfo) in &self.abs {
+                check(ioctl(fd, UI_SET_ABSBIT, *code))?;
+                let setup = uinput_abs_setup {
+                    code: *code as u16,
+                    absinfo: *absinfo,
+                };
+                check(ioctl(fd, UI_ABS_SETUP, &setup))?;
+            }
+            check(ioctl(fd, UI_DEV_SETUP, &definition))?;
+            check(ioctl(fd, UI_DEV_CREATE))?;
+        }
+        Ok(IoCtl(
+            file,
+            timeval {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            Vec::with_capacity(100),
+            (0, 0),
+        ))
+    }
+}
+
+/// the largest value of `ABS_X` and `ABS_Y` reported by `AbsPointer`.
+pub const ABS_RANGE: i32 = 0xffff;
+
+/// the device value of the logical position `(x, y)` on the outputs bounded by `bounds`, see `AbsPointer::to_abs`.
+///
+/// libinput maps a value `v` to `v * size / (ABS_RANGE + 1)`, thus `ABS_RANGE + 1` values cover the size.
+pub(crate) fn to_abs(bounds: &Rect, x: f64, y: f64) -> (i32, i32) {
+    let scale = |v: f64, origin: f64, size: f64| {
+        if size > 0.0 {
+            ((v - origin) / size * (ABS_RANGE as f64 + 1.0))
+                .round()
+                .clamp(0.0, ABS_RANGE as f64) as i32
+        } else {
+            0
+        }
+    };
+    (
+        scale(x, bounds.x, bounds.width),
+        scale(y, bounds.y, bounds.height),
+    )
+}
+
+/// a virtual absolute pointer (like the tablet of a virtual machine), whose position is not accelerated.
+///
+/// KWin scales absolute positions onto the size of the union of all outputs, thus `move_to` warps the cursor exactly.
+/// ```no_run
+/// use kwin_mouse_loc::{device::AbsPointer, pointer::Rect};
+/// let mut pointer = AbsPointer::new(&[Rect::new(0.0, 0.0, 1920.0, 1080.0), Rect::new(1920.0, 0.0, 1280.0, 1024.0)]);
+/// pointer.move_to(2000.0, 500.0);
+/// ```
+pub struct AbsPointer(IoCtl, Rect);
+impl AbsPointer {
+    /// create the device for the output geometries `outputs` in logical coordinates, e.g., from `kscreen-doctor -o`.
+    pub fn new(outputs: &[Rect]) -> Self {
+        let bounds = Rect::bounding(outputs).unwrap_or(Rect::new(0.0, 0.0, 0.0, 0.0));
+        // libinput treats devices with ABS_X, ABS_Y and buttons (but no tool) as absolute pointers.
+        let device = DeviceBuilder::new()
+            .name("my-virtual-tablet")
+            .keys([BTN_LEFT, BTN_RIGHT, BTN_MIDDLE])
+            .wheel()
+            .abs(ABS_X, 0, ABS_RANGE, 0)
+            .abs(ABS_Y, 0, ABS_RANGE, 0)
+            .build()
+            .unwrap();
+        Self(device, bounds)
+    }
+    /// the device value of the logical position `(x, y)`, clamped into the outputs.
+    pub fn to_abs(&self, x: f64, y: f64) -> (i32, i32) {
+        to_abs(&self.1, x, y)
+    }
+    /// move the cursor to the logical position `(x, y)`.
+    pub fn move_to(&mut self, x: f64, y: f64) {
+        let (x, y) = self.to_abs(x, y);
+        self.0.event(EV_ABS as u16, ABS_X as u16, x);
+        self.0.send(EV_ABS as u16, ABS_Y as u16, y);
+    }
+    pub fn press(&mut self, btn: impl IntoU16) {
+        self.0.press(btn)
+    }
+    pub fn release(&mut self, btn: impl IntoU16) {
+        self.0.release(btn)
+    }
+    pub fn click(&mut self, btn: impl IntoU16, half_dur: Duration) {
+        self.0.click(btn, half_dur)
+    }
+    /// see `IoCtl::scroll`.
+    pub fn scroll(&mut self, vertical: i32, horizontal: i32) {
+        self.0.scroll(vertical, horizontal)
+    }
+    /// see `IoCtl::scroll_hi_res`.
+    pub fn scroll_hi_res(&mut self, vertical: i32, horizontal: i32) {
+        self.0.scroll_hi_res(vertical, horizontal)
+    }
+}
+
+/// modifiers held while typing a character.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Modifiers {
+    /// `KEY_LEFTSHIFT`.
+    pub shift: bool,
+    /// AltGr, i.e., `KEY_RIGHTALT`.
+    pub altgr: bool,
+}
+impl Modifiers {
+    /// no modifier.
+    pub const NONE: Self = Self {
+        shift: false,
+        altgr: false,
+    };
+    /// shift only.
+    pub const SHIFT: Self = Self {
+        shift: true,
+        altgr: false,
+    };
+    /// AltGr only.
+    pub const ALTGR: Self = Self {
+        shift: false,
+        altgr: true,
+    };
+    fn keys(&self) -> impl Iterator<Item = u32> {
+        [(self.shift, KEY_LEFTSHIFT), (self.altgr, KEY_RIGHTALT)]
+            .into_iter()
+            .filter_map(|x| x.0.then_some(x.1))
+    }
+}
+
+/// the key and modifiers producing each character, should match the layout configured in kwin.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout(std::collections::HashMap<char, (u32, Modifiers)>);
+impl Default for Layout {
+    fn default() -> Self {
+        Self::us()
+    }
+}
+impl Layout {
+    /// a layout producing nothing.
+    pub fn empty() -> Self {
+        Self(std::collections::HashMap::new())
+    }
+    /// the US layout.
+    pub fn us() -> Self {
+        let letters = [
+            KEY_A, KEY_B, KEY_C, KEY_D, KEY_E, KEY_F, KEY_G, KEY_H, KEY_I, KEY_J, KEY_K, KEY_L,
+            KEY_M, KEY_N, KEY_O, KEY_P, KEY_Q, KEY_R, KEY_S, KEY_T, KEY_U, KEY_V, KEY_W, KEY_X,
+            KEY_Y, KEY_Z,
+        ];
+        let digits = [
+            KEY_0, KEY_1, KEY_2, KEY_3, KEY_4, KEY_5, KEY_6, KEY_7, KEY_8, KEY_9,
+        ];
+        let mut ret = Self::empty();
+        for (key, c) in letters.into_iter().zip('a'..='z') {
+            ret = ret.with(c, key, Modifiers::NONE).with(
+                c.to_ascii_uppercase(),
+                key,
+                Modifiers::SHIFT,
+            );
+        }
+        for (key, (c, shifted)) in digits
+            .into_iter()
+            .zip("0123456789".chars().zip(")!@#$%^&*(".chars()))
+        {
+            ret = ret
+                .with(c, key, Modifiers::NONE)
+                .with(shifted, key, Modifiers::SHIFT);
+        }
+        for (key, c, shifted) in [
+            (KEY_MINUS, '-', '_'),
+            (KEY_EQUAL, '=', '+'),
+            (KEY_LEFTBRACE, '[', '{'),
+            (KEY_RIGHTBRACE, ']', '}'),
+            (KEY_BACKSLASH, '\\', '|'),
+            (KEY_SEMICOLON, ';', ':'),
+            (KEY_APOSTROPHE, '\'', '"'),
+            (KEY_GRAVE, '`', '~'),
+            (KEY_COMMA, ',', '<'),
+            (KEY_DOT, '.', '>'),
+            (KEY_SLASH, '/', '?'),
+        ] {
+            ret = ret
+                .with(c, key, Modifiers::NONE)
+                .with(shifted, key, Modifiers::SHIFT);
+        }
+        ret.with(' ', KEY_SPACE, Modifiers::NONE)
+            .with('\n', KEY_ENTER, Modifiers::NONE)
+            .with('\t', KEY_TAB, Modifiers::NONE)
+    }
+    /// produce `c` by `key` with `modifiers`, e.g., `Layout::us().with('€', KEY_5, Modifiers::ALTGR)`.
+    pub fn with(mut self, c: char, key: u32, modifiers: Modifiers) -> Self {
+        self.0.insert(c, (key, modifiers));
+        self
+    }
+    /// the key and modifiers producing `c`.
+    pub fn get(&self, c: char) -> Option<(u32, Modifiers)> {
+        self.0.get(&c).copied()
+    }
+    /// characters of `text` this layout cannot produce, with their byte offsets.
+    pub fn unsupported(&self, text: &str) -> Vec<(usize, char)> {
+        text.char_indices()
+            .filter(|x| !self.0.contains_key(&x.1))
+            .collect()
+    }
+}
+
+/// characters `IoCtl::type_text` cannot produce, with their byte offsets in the text.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unsupported(pub Vec<(usize, char)>);
+impl std::fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot type")?;
+        for (i, c) in &self.0 {
+            write!(f, " {c:?} (at {i})")?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for Unsupported {}
+
+impl IoCtl {
+    /// type `text` with the US layout, see `type_text_with`.
+    pub fn type_text(&mut self, text: &str, per_key_delay: Duration) -> Result<(), Unsupported> {
+        self.type_text_with(&Layout::us(), text, per_key_delay)
+    }
+    /// type `text` with `layout`, holding shift or AltGr when a character needs them.
+    ///
+    /// Nothing is typed if any character cannot be produced. The keys should be registered (e.g., the `keyboard`
+    /// feature, or `DeviceBuilder::keyboard`), and `layout` should match the one configured in kwin.
+    pub fn type_text_with(
+        &mut self,
+        layout: &Layout,
+        text: &str,
+        per_key_delay: Duration,
+    ) -> Result<(), Unsupported> {
+        let unsupported = layout.unsupported(text);
+        if !unsupported.is_empty() {
+            return Err(Unsupported(unsupported));
+        }
+        for (key, modifiers) in text.chars().filter_map(|c| layout.get(c)) {
+            for x in modifiers.keys() {
+                self.press(x);
+            }
+            self.click(key, per_key_delay / 2);
+            for x in modifiers.keys() {
+                self.release(x);
+            }
+        }
+        Ok(())
+    }
+}
+
 pub trait IntoU16: Copy {
     fn into(self) -> u16;
 }
@@ -0,0 +1,614 @@
+// This file only relies on `std` and `elf`, since it is also used by `build.rs` (through `#[path]`).
+use crate::elf::{self, Elf, SHT_NOBITS};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// where the separate debug files are installed.
+pub const DEBUG_ROOT: &str = "/usr/lib/debug";
+
+const DW_TAG_CLASS_TYPE: u64 = 0x02;
+const DW_TAG_MEMBER: u64 = 0x0d;
+const DW_TAG_STRUCTURE_TYPE: u64 = 0x13;
+const DW_TAG_UNION_TYPE: u64 = 0x17;
+const DW_TAG_NAMESPACE: u64 = 0x39;
+const DW_AT_NAME: u64 = 0x03;
+const DW_AT_BYTE_SIZE: u64 = 0x0b;
+const DW_AT_DATA_MEMBER_LOCATION: u64 = 0x38;
+const DW_AT_STR_OFFSETS_BASE: u64 = 0x72;
+const DW_FORM_IMPLICIT_CONST: u64 = 0x21;
+const DW_OP_PLUS_UCONST: u8 = 0x23;
+
+/// errors of reading DWARF.
+#[derive(Debug)]
+pub enum Error {
+    /// the library or its debug file cannot be parsed.
+    Elf(elf::Error),
+    /// neither the library nor any separate debug file contains `.debug_info`.
+    NoDebugFile,
+    /// the dwz supplementary file named by `.gnu_debugaltlink` cannot be found.
+    NoAltFile(String),
+    /// some entry points outside of its section.
+    Truncated,
+    /// an attribute form this reader does not know.
+    UnknownForm(u64),
+    /// the member cannot be found, or its location is not a constant.
+    MemberNotFound(String),
+    /// no definition of the type is found, with its scoped name.
+    TypeNotFound(String),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Elf(e) => write!(f, "{e}"),
+            Self::NoDebugFile => write!(
+                f,
+                "cannot find debug info (is the debug package installed?)"
+            ),
+            Self::NoAltFile(x) => write!(f, "cannot find the supplementary debug file {x}"),
+            Self::Truncated => write!(f, "debug info is truncated or damaged"),
+            Self::UnknownForm(x) => write!(f, "unknown attribute form 0x{x:x}"),
+            Self::MemberNotFound(x) => write!(f, "cannot find member {x}"),
+            Self::TypeNotFound(x) => write!(f, "cannot find the definition of {x}"),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Elf(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<elf::Error> for Error {
+    fn from(e: elf::Error) -> Self {
+        Self::Elf(e)
+    }
+}
+
+/// the file holding the DWARF of `path`: itself if not stripped, otherwise the separate debug file under `DEBUG_ROOT`.
+pub fn find_debug_file(path: impl AsRef<Path>) -> Result<PathBuf, Error> {
+    find_debug_file_in(path, DEBUG_ROOT)
+}
+/// like `find_debug_file`, with the separate debug files installed in `root`.
+///
+/// The build-id path (`.build-id/xx/yyyy.debug`) is tried first, then `.gnu_debuglink` in the directory of
+/// the library, its `.debug` subdirectory, and the same directory under `root`. Debuglinks are verified by CRC32.
+pub fn find_debug_file_in(
+    path: impl AsRef<Path>,
+    root: impl AsRef<Path>,
+) -> Result<PathBuf, Error> {
+    let path = fs::canonicalize(path.as_ref()).map_err(elf::Error::Io)?;
+    let root = root.as_ref();
+    let lib = Elf::open(&path)?;
+    if has_debug_info(&lib) {
+        return Ok(path);
+    }
+    if let Some(file) = lib.build_id().and_then(|x| build_id_path(root, x))
+        && Elf::open(&file).is_ok_and(|x| has_debug_info(&x))
+    {
+        return Ok(file);
+    }
+    if let Some((name, crc)) = lib.debuglink() {
+        let dir = path.parent().unwrap_or(Path::new("/"));
+        for file in [
+            dir.join(name),
+            dir.join(".debug").join(name),
+            root.join(dir.strip_prefix("/").unwrap_or(dir)).join(name),
+        ] {
+            if file != path && fs::read(&file).is_ok_and(|x| crc32(&x) == crc) {
+                return Ok(file);
+            }
+        }
+    }
+    Err(Error::NoDebugFile)
+}
+/// the dwz supplementary file named by `.gnu_debugaltlink` of `elf`, `None` if there is no such link.
+///
+/// A relative link is resolved in `dir` (the directory of the debug file), then the build-id path under `root`
+/// is tried. The build-id of the found file must match the link.
+pub fn find_alt_file_in(
+    elf: &Elf,
+    dir: Option<&Path>,
+    root: impl AsRef<Path>,
+) -> Result<Option<PathBuf>, Error> {
+    let Some((name, id)) = elf.debugaltlink() else {
+        return Ok(None);
+    };
+    let link = Path::new(name);
+    let candidates = [
+        link.is_absolute().then(|| link.to_owned()),
+        dir.filter(|_| link.is_relative()).map(|x| x.join(link)),
+        build_id_path(root.as_ref(), id),
+    ];
+    candidates
+        .into_iter()
+        .flatten()
+        .find(|x| Elf::open(x).is_ok_and(|x| x.build_id() == Some(id)))
+        .map(Some)
+        .ok_or_else(|| Error::NoAltFile(name.to_owned()))
+}
+/// `.build-id/xx/yyyy.debug` under `root`.
+fn build_id_path(root: &Path, id: &[u8]) -> Option<PathBuf> {
+    let hex = id.iter().map(|x| format!("{x:02x}")).collect::<String>();
+    (id.len() > 1).then(|| root.join(format!(".build-id/{}/{}.debug", &hex[..2], &hex[2..])))
+}
+fn has_debug_info(elf: &Elf) -> bool {
+    elf.section(".debug_info")
+        .is_some_and(|x| x.kind != SHT_NOBITS && x.size > 0)
+}
+/// CRC32 used by `.gnu_debuglink`.
+pub fn crc32(data: &[u8]) -> u32 {
+    let table = (0..256u32)
+        .map(|i| {
+            (0..8).fold(i, |c, _| {
+                if c & 1 == 1 {
+                    0xedb8_8320 ^ c >> 1
+                } else {
+                    c >> 1
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    !data.iter().fold(!0u32, |c, &x| {
+        table[((c ^ x as u32) & 0xff) as usize] ^ c >> 8
+    })
+}
+
+/// read the offset of `focusMousePos` in `KWin::Workspace` from the DWARF of libkwin.so (located at `path`).
+pub fn focus_mouse_pos_offset(path: impl AsRef<Path>) -> Result<usize, Error> {
+    let (elf, dir) = open_debug_file(path)?;
+    let scope = ["KWin", "Workspace"];
+    search(&elf, dir.as_deref(), &scope, Some(b"focusMousePos"))?
+        .map(|x| x as usize)
+        .ok_or_else(|| Error::MemberNotFound("KWin::Workspace::focusMousePos".into()))
+}
+
+/// the size of `KWin::Workspace` from the DWARF of libkwin.so (located at `path`).
+pub fn workspace_size(path: impl AsRef<Path>) -> Result<usize, Error> {
+    let (elf, dir) = open_debug_file(path)?;
+    search(&elf, dir.as_deref(), &["KWin", "Workspace"], None)?
+        .map(|x| x as usize)
+        .ok_or_else(|| Error::TypeNotFound("KWin::Workspace".into()))
+}
+
+/// `DW_AT_data_member_location` of `member` in the class or struct whose scope ends with `scope` (e.g., `["KWin", "Workspace"]`).
+///
+/// Entries moved into a dwz supplementary file are searched as well, a relative `.gnu_debugaltlink` is only
+/// resolved by build-id here since the directory of `elf` is unknown.
+pub fn member_offset(elf: &Elf, scope: &[&str], member: &str) -> Result<u64, Error> {
+    search(elf, None, scope, Some(member.as_bytes()))?
+        .ok_or_else(|| Error::MemberNotFound(format!("{}::{member}", scope.join("::"))))
+}
+
+/// `DW_AT_byte_size` of the class or struct whose scope ends with `scope`, declarations are skipped.
+pub fn type_size(elf: &Elf, scope: &[&str]) -> Result<u64, Error> {
+    search(elf, None, scope, None)?.ok_or_else(|| Error::TypeNotFound(scope.join("::")))
+}
+
+/// the debug file of `path` and its real directory, where relative `.gnu_debugaltlink`s start.
+fn open_debug_file(path: impl AsRef<Path>) -> Result<(Elf, Option<PathBuf>), Error> {
+    let file = fs::canonicalize(find_debug_file(path)?).map_err(elf::Error::Io)?;
+    Ok((Elf::open(&file)?, file.parent().map(Path::to_owned)))
+}
+
+/// `Dwarf::search` through `elf`, then through its dwz supplementary file.
+fn search(
+    elf: &Elf,
+    dir: Option<&Path>,
+    scope: &[&str],
+    member: Option<&[u8]>,
+) -> Result<Option<u64>, Error> {
+    let alt = match find_alt_file_in(elf, dir, DEBUG_ROOT)? {
+        Some(x) => Some(Elf::open(x)?),
+        None => None,
+    };
+    let alt = alt.as_ref().map(|x| Dwarf::of(x, None)).transpose()?;
+    match Dwarf::of(elf, alt.as_ref())?.search(scope, member)? {
+        Some(x) => Ok(Some(x)),
+        None => alt.map_or(Ok(None), |x| x.search(scope, member)),
+    }
+}
+
+/// whether the innermost entries of `path` are named by `scope`, through namespaces, classes, structs or unions.
+fn in_scope(path: &[(u64, &[u8])], scope: &[&str]) -> bool {
+    path.len() >= scope.len()
+        && path[path.len() - scope.len()..]
+            .iter()
+            .zip(scope)
+            .all(|(x, s)| {
+                matches!(
+                    x.0,
+                    DW_TAG_NAMESPACE
+                        | DW_TAG_CLASS_TYPE
+                        | DW_TAG_STRUCTURE_TYPE
+                        | DW_TAG_UNION_TYPE
+                ) && x.1 == s.as_bytes()
+            })
+}
+
+struct Dwarf<'a> {
+    info: Cow<'a, [u8]>,
+    abbrev: Cow<'a, [u8]>,
+    str: Cow<'a, [u8]>,
+    line_str: Cow<'a, [u8]>,
+    str_offsets: Cow<'a, [u8]>,
+    /// `.debug_str` of the dwz supplementary file, for `DW_FORM_GNU_strp_alt` and `DW_FORM_strp_sup`.
+    alt_str: &'a [u8],
+    le: bool,
+}
+struct Abbrev {
+    tag: u64,
+    children: bool,
+    /// (name, form, implicit const)
+    attrs: Vec<(u64, u64, i64)>,
+}
+enum Value<'a> {
+    Uint(u64),
+    Int(i64),
+    Block(&'a [u8]),
+    Str(&'a [u8]),
+    StrIndex(u64),
+    Other,
+}
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    le: bool,
+}
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let ret = self
+            .data
+            .get(self.pos..self.pos.checked_add(len).ok_or(Error::Truncated)?)
+            .ok_or(Error::Truncated)?;
+        self.pos += len;
+        Ok(ret)
+    }
+    fn uint(&mut self, len: usize) -> Result<u64, Error> {
+        let it = self.bytes(len)?.iter().map(|&x| x as u64);
+        Ok(if self.le {
+            it.rev().fold(0, |acc, x| acc << 8 | x)
+        } else {
+            it.fold(0, |acc, x| acc << 8 | x)
+        })
+    }
+    fn uleb(&mut self) -> Result<u64, Error> {
+        let (mut ret, mut shift) = (0u64, 0);
+        loop {
+            let x = self.bytes(1)?[0];
+            if shift < 64 {
+                ret |= ((x & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if x & 0x80 == 0 {
+                return Ok(ret);
+            }
+        }
+    }
+    fn sleb(&mut self) -> Result<i64, Error> {
+        let (mut ret, mut shift) = (0i64, 0);
+        loop {
+            let x = self.bytes(1)?[0];
+            if shift < 64 {
+                ret |= ((x & 0x7f) as i64) << shift;
+            }
+            shift += 7;
+            if x & 0x80 == 0 {
+                if shift < 64 && x & 0x40 != 0 {
+                    ret |= -1 << shift;
+                }
+                return Ok(ret);
+            }
+        }
+    }
+    fn cstr(&mut self) -> Result<&'a [u8], Error> {
+        let data = self.data.get(self.pos..).ok_or(Error::Truncated)?;
+        let len = data.iter().position(|&x| x == 0).ok_or(Error::Truncated)?;
+        self.pos += len + 1;
+        Ok(&data[..len])
+    }
+}
+fn cstr_at(data: &[u8], off: u64) -> Result<&[u8], Error> {
+    Reader {
+        data,
+        pos: off as usize,
+        le: true,
+    }
+    .cstr()
+}
+impl<'a> Dwarf<'a> {
+    fn of(elf: &'a Elf, alt: Option<&'a Dwarf>) -> Result<Self, Error> {
+        let section = |name: &str| -> Result<Cow<'a, [u8]>, Error> {
+            match elf.section(name) {
+                Some(x) if x.kind != SHT_NOBITS => Ok(elf.section_contents(x)?),
+                _ => Ok(Cow::Borrowed(&[])),
+            }
+        };
+        let ret = Self {
+            info: section(".debug_info")?,
+            abbrev: section(".debug_abbrev")?,
+            str: section(".debug_str")?,
+            line_str: section(".debug_line_str")?,
+            str_offsets: section(".debug_str_offsets")?,
+            alt_str: alt.map_or(&[], |x| &x.str),
+            le: elf.little_endian(),
+        };
+        if ret.info.is_empty() {
+            return Err(Error::NoDebugFile);
+        }
+        Ok(ret)
+    }
+    /// the location of `member` in `scope`, or the size of `scope` if `member` is `None`, through every unit.
+    fn search(&self, scope: &[&str], member: Option<&[u8]>) -> Result<Option<u64>, Error> {
+        let mut off = 0;
+        while off < self.info.len() {
+            let (found, next) = self.unit(off, scope, member)?;
+            if found.is_some() {
+                return Ok(found);
+            }
+            off = next;
+        }
+        Ok(None)
+    }
+    fn abbrevs(&self, off: u64) -> Result<HashMap<u64, Abbrev>, Error> {
+        let mut r = Reader {
+            data: &self.abbrev,
+            pos: off as usize,
+            le: self.le,
+        };
+        let mut ret = HashMap::new();
+        loop {
+            let code = r.uleb()?;
+            if code == 0 {
+                return Ok(ret);
+            }
+            let tag = r.uleb()?;
+            let children = r.bytes(1)?[0] != 0;
+            let mut attrs = Vec::new();
+            loop {
+                let (name, form) = (r.uleb()?, r.uleb()?);
+                let value = if form == DW_FORM_IMPLICIT_CONST {
+                    r.sleb()?
+                } else {
+                    0
+                };
+                if name == 0 && form == 0 {
+                    break;
+                }
+                attrs.push((name, form, value));
+            }
+            ret.insert(
+                code,
+                Abbrev {
+                    tag,
+                    children,
+                    attrs,
+                },
+            );
+        }
+    }
+    /// search the unit at `off`, returns the member location (or type size) if found, and the offset of the next unit.
+    fn unit<'s>(
+        &'s self,
+        off: usize,
+        scope: &[&str],
+        member: Option<&[u8]>,
+    ) -> Result<(Option<u64>, usize), Error> {
+        let mut r = Reader {
+            data: &self.info,
+            pos: off,
+            le: self.le,
+        };
+        let (len, offset_size) = match r.uint(4)? {
+            0xffff_ffff => (r.uint(8)?, 8),
+            x => (x, 4),
+        };
+        let next = r.pos.checked_add(len as usize).ok_or(Error::Truncated)?;
+        r.data = self.info.get(..next).ok_or(Error::Truncated)?;
+        let version = r.uint(2)?;
+        let (abbrev, address_size) = if version >= 5 {
+            let kind = r.bytes(1)?[0];
+            let address_size = r.bytes(1)?[0];
+            let abbrev = r.uint(offset_size)?;
+            // DW_UT_type, DW_UT_skeleton, DW_UT_split_compile, DW_UT_split_type
+            match kind {
+                2 | 6 => r.pos += 8 + offset_size,
+                4 | 5 => r.pos += 8,
+                _ => {}
+            }
+            (abbrev, address_size)
+        } else {
+            (r.uint(offset_size)?, r.bytes(1)?[0])
+        };
+        let abbrevs = self.abbrevs(abbrev)?;
+        let unit = Unit {
+            version,
+            offset_size,
+            address_size: address_size as usize,
+        };
+        let mut str_offsets_base = None;
+        // (tag, name) of the entries having children.
+        let mut stack: Vec<(u64, &'s [u8])> = Vec::new();
+        while r.pos < next {
+            let code = r.uleb()?;
+            if code == 0 {
+                stack.pop();
+                continue;
+            }
+            let abbrev = abbrevs.get(&code).ok_or(Error::Truncated)?;
+            let (mut name, mut location, mut size) = (None, None, None);
+            for &(attr, f, implicit) in &abbrev.attrs {
+                let value = self.value(&mut r, f, implicit, &unit)?;
+                match attr {
+                    DW_AT_NAME => name = Some(value),
+                    DW_AT_DATA_MEMBER_LOCATION => location = Some(value),
+                    DW_AT_BYTE_SIZE => size = Some(value),
+                    DW_AT_STR_OFFSETS_BASE => {
+                        if let Value::Uint(x) = value {
+                            str_offsets_base = Some(x)
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            let name = match name {
+                Some(Value::Str(x)) => x,
+                Some(Value::StrIndex(i)) => match str_offsets_base {
+                    Some(base) => {
+                        let mut o = Reader {
+                            data: &self.str_offsets,
+                            pos: (base + i * offset_size as u64) as usize,
+                            le: self.le,
+                        };
+                        cstr_at(&self.str, o.uint(offset_size)?)?
+                    }
+                    None => b"",
+                },
+                _ => b"",
+            };
+            let class = matches!(abbrev.tag, DW_TAG_CLASS_TYPE | DW_TAG_STRUCTURE_TYPE);
+            if member.is_none() && class {
+                stack.push((abbrev.tag, name));
+                let found = in_scope(&stack, scope);
+                stack.pop();
+                if found && let Some(Value::Uint(x)) = size {
+                    return Ok((Some(x), next));
+                }
+            }
+            if abbrev.tag == DW_TAG_MEMBER
+                && member == Some(name)
+                && stack
+                    .last()
+                    .is_some_and(|x| matches!(x.0, DW_TAG_CLASS_TYPE | DW_TAG_STRUCTURE_TYPE))
+                && in_scope(&stack, scope)
+            {
+                match location {
+                    Some(Value::Uint(x)) => return Ok((Some(x), next)),
+                    Some(Value::Int(x)) if x >= 0 => return Ok((Some(x as u64), next)),
+                    // DWARF 2 style: DW_OP_plus_uconst
+                    Some(Value::Block(x)) if x.first() == Some(&DW_OP_PLUS_UCONST) => {
+                        let mut r = Reader {
+                            data: x,
+                            pos: 1,
+                            le: self.le,
+                        };
+                        return Ok((Some(r.uleb()?), next));
+                    }
+                    _ => {}
+                }
+            }
+            if abbrev.children {
+                stack.push((abbrev.tag, name));
+            }
+        }
+        Ok((None, next))
+    }
+    fn value<'s>(
+        &'s self,
+        r: &mut Reader<'s>,
+        form: u64,
+        implicit: i64,
+        unit: &Unit,
+    ) -> Result<Value<'s>, Error> {
+        let off = unit.offset_size;
+        Ok(match form {
+            0x0b => Value::Uint(r.uint(1)?),
+            0x05 => Value::Uint(r.uint(2)?),
+            0x06 => Value::Uint(r.uint(4)?),
+            0x07 => Value::Uint(r.uint(8)?),
+            0x0f => Value::Uint(r.uleb()?),
+            0x0d => Value::Int(r.sleb()?),
+            DW_FORM_IMPLICIT_CONST => Value::Int(implicit),
+            // sec_offset
+            0x17 => Value::Uint(r.uint(off)?),
+            0x08 => Value::Str(r.cstr()?),
+            0x0e => Value::Str(cstr_at(&self.str, r.uint(off)?)?),
+            0x1f => Value::Str(cstr_at(&self.line_str, r.uint(off)?)?),
+            // strp_sup, GNU_strp_alt: strings of the dwz supplementary file.
+            0x1d | 0x1f21 => match r.uint(off)? {
+                _ if self.alt_str.is_empty() => Value::Other,
+                x => Value::Str(cstr_at(self.alt_str, x)?),
+            },
+            0x1a | 0x1f02 => Value::StrIndex(r.uleb()?),
+            0x25..=0x28 => Value::StrIndex(r.uint(form as usize - 0x24)?),
+            0x0a => Value::Block({
+                let len = r.uint(1)?;
+                r.bytes(len as usize)?
+            }),
+            0x03 => Value::Block({
+                let len = r.uint(2)?;
+                r.bytes(len as usize)?
+            }),
+            0x04 => Value::Block({
+                let len = r.uint(4)?;
+                r.bytes(len as usize)?
+            }),
+            0x09 | 0x18 => Value::Block({
+                let len = r.uleb()?;
+                r.bytes(len as usize)?
+            }),
+            0x01 => {
+                r.bytes(unit.address_size)?;
+                Value::Other
+            }
+            0x10 => {
+                r.bytes(if unit.version <= 2 {
+                    unit.address_size
+                } else {
+                    off
+                })?;
+                Value::Other
+            }
+            // GNU_ref_alt: the units of the supplementary file are searched on their own.
+            0x1f20 => {
+                r.bytes(off)?;
+                Value::Other
+            }
+            0x0c | 0x11 | 0x29 => {
+                r.bytes(1)?;
+                Value::Other
+            }
+            0x12 | 0x2a => {
+                r.bytes(2)?;
+                Value::Other
+            }
+            0x2b => {
+                r.bytes(3)?;
+                Value::Other
+            }
+            0x13 | 0x1c | 0x2c => {
+                r.bytes(4)?;
+                Value::Other
+            }
+            0x14 | 0x20 | 0x24 => {
+                r.bytes(8)?;
+                Value::Other
+            }
+            0x1e => {
+                r.bytes(16)?;
+                Value::Other
+            }
+            0x15 | 0x1b | 0x22 | 0x23 | 0x1f01 => {
+                r.uleb()?;
+                Value::Other
+            }
+            0x19 => Value::Other,
+            0x16 => {
+                let form = r.uleb()?;
+                self.value(r, form, implicit, unit)?
+            }
+            x => return Err(Error::UnknownForm(x)),
+        })
+    }
+}
+struct Unit {
+    version: u64,
+    offset_size: usize,
+    address_size: usize,
+}
@@ -0,0 +1,484 @@
+// This file only relies on `std`, since it is also used by `build.rs` (through `#[path]`).
+use std::{
+    borrow::Cow,
+    ffi::{CStr, c_char, c_int, c_uint, c_ulong, c_void},
+    fmt::Display,
+    fs, io,
+    path::Path,
+};
+
+/// mangled name of `KWin::Workspace::_self`.
+pub const WORKSPACE_SELF: &str = "_ZN4KWin9Workspace5_selfE";
+
+/// `SHT_SYMTAB`
+pub const SHT_SYMTAB: u32 = 2;
+/// `SHT_DYNSYM`
+pub const SHT_DYNSYM: u32 = 11;
+/// `SHT_NOTE`
+pub const SHT_NOTE: u32 = 7;
+/// `SHT_NOBITS`
+pub const SHT_NOBITS: u32 = 8;
+/// `SHT_GNU_HASH`
+pub const SHT_GNU_HASH: u32 = 0x6fff_fff6;
+/// `PT_LOAD`
+pub const PT_LOAD: u32 = 1;
+/// `SHF_COMPRESSED`
+pub const SHF_COMPRESSED: u64 = 0x800;
+/// `ELFCOMPRESS_ZLIB`
+pub const ELFCOMPRESS_ZLIB: u32 = 1;
+/// `ELFCOMPRESS_ZSTD`
+pub const ELFCOMPRESS_ZSTD: u32 = 2;
+/// `NT_GNU_BUILD_ID`
+pub const NT_GNU_BUILD_ID: u32 = 3;
+
+/// errors of parsing an ELF file.
+#[derive(Debug)]
+pub enum Error {
+    /// the file cannot be read.
+    Io(io::Error),
+    /// the file is not an ELF file.
+    NotElf,
+    /// the ELF file is valid, but not supported by this small reader (e.g., ELF32).
+    Unsupported(&'static str),
+    /// some header points outside of the file.
+    Truncated,
+    /// the symbol cannot be found in `.dynsym` and `.symtab`.
+    SymbolNotFound(String),
+    /// a compressed section cannot be decompressed, with the section name and the reason.
+    Decompress(String, String),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "cannot read elf file: {e}"),
+            Self::NotElf => write!(f, "not an elf file"),
+            Self::Unsupported(x) => write!(f, "unsupported elf file: {x}"),
+            Self::Truncated => write!(f, "elf file is truncated or damaged"),
+            Self::SymbolNotFound(x) => write!(f, "cannot find symbol {x}"),
+            Self::Decompress(x, e) => write!(f, "cannot decompress section {x}: {e}"),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// a section header.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Section {
+    /// name from `.shstrtab`.
+    pub name: String,
+    /// `sh_type`
+    pub kind: u32,
+    /// `sh_flags`
+    pub flags: u64,
+    /// `sh_addr`
+    pub addr: u64,
+    /// `sh_offset`, the file offset.
+    pub offset: u64,
+    /// `sh_size`
+    pub size: u64,
+    /// `sh_link`
+    pub link: u32,
+    /// `sh_entsize`
+    pub entsize: u64,
+}
+
+/// a program header.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Segment {
+    /// `p_type`
+    pub kind: u32,
+    /// `p_flags`
+    pub flags: u32,
+    /// `p_offset`
+    pub offset: u64,
+    /// `p_vaddr`
+    pub vaddr: u64,
+    /// `p_filesz`
+    pub filesz: u64,
+    /// `p_memsz`
+    pub memsz: u64,
+    /// `p_align`
+    pub align: u64,
+}
+
+/// a symbol found by `Elf::lookup`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Symbol {
+    /// `st_value`, for shared objects it is the address relative to the load bias.
+    pub value: u64,
+    /// `st_size`
+    pub size: u64,
+}
+
+/// a small ELF64 reader, which holds the whole file.
+pub struct Elf {
+    data: Vec<u8>,
+    le: bool,
+    sections: Vec<Section>,
+    segments: Vec<Segment>,
+}
+impl Elf {
+    /// read and parse the file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::parse(fs::read(path)?)
+    }
+    /// parse the ELF64 file in `data`.
+    pub fn parse(data: Vec<u8>) -> Result<Self, Error> {
+        if data.len() < 0x40 || data[0..4] != *b"\x7fELF" {
+            return Err(Error::NotElf);
+        }
+        if data[4] != 2 {
+            return Err(Error::Unsupported("only ELF64 is supported"));
+        }
+        let le = match data[5] {
+            1 => true,
+            2 => false,
+            _ => return Err(Error::Unsupported("unknown endianness")),
+        };
+        let mut ret = Self {
+            data,
+            le,
+            sections: Vec::new(),
+            segments: Vec::new(),
+        };
+        ret.sections = ret.read_sections()?;
+        ret.segments = ret.read_segments()?;
+        Ok(ret)
+    }
+    /// the raw content of the file.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    /// whether the file is little endian.
+    pub fn little_endian(&self) -> bool {
+        self.le
+    }
+    /// `len` bytes at file offset `off`.
+    pub fn bytes(&self, off: u64, len: u64) -> Result<&[u8], Error> {
+        let start = usize::try_from(off).map_err(|_| Error::Truncated)?;
+        let end = usize::try_from(len)
+            .ok()
+            .and_then(|x| start.checked_add(x))
+            .ok_or(Error::Truncated)?;
+        self.data.get(start..end).ok_or(Error::Truncated)
+    }
+    /// read an unsigned integer of `N` bytes at `off` with the file's endianness.
+    pub fn uint<const N: usize>(&self, off: u64) -> Result<u64, Error> {
+        let bytes = self.bytes(off, N as u64)?;
+        let it = bytes.iter().map(|&x| x as u64);
+        Ok(if self.le {
+            it.rev().fold(0, |acc, x| acc << 8 | x)
+        } else {
+            it.fold(0, |acc, x| acc << 8 | x)
+        })
+    }
+    fn u16(&self, off: u64) -> Result<u64, Error> {
+        self.uint::<2>(off)
+    }
+    fn u32(&self, off: u64) -> Result<u64, Error> {
+        self.uint::<4>(off)
+    }
+    fn u64(&self, off: u64) -> Result<u64, Error> {
+        self.uint::<8>(off)
+    }
+    fn read_segments(&self) -> Result<Vec<Segment>, Error> {
+        let phoff = self.u64(0x20)?;
+        let phentsize = self.u16(0x36)?;
+        let phnum = self.u16(0x38)?;
+        if phoff == 0 {
+            return Ok(Vec::new());
+        }
+        (0..phnum)
+            .map(|i| {
+                let h = phoff + i * phentsize;
+                Ok(Segment {
+                    kind: self.u32(h)? as u32,
+                    flags: self.u32(h + 4)? as u32,
+                    offset: self.u64(h + 8)?,
+                    vaddr: self.u64(h + 16)?,
+                    filesz: self.u64(h + 32)?,
+                    memsz: self.u64(h + 40)?,
+                    align: self.u64(h + 48)?,
+                })
+            })
+            .collect()
+    }
+    fn read_sections(&self) -> Result<Vec<Section>, Error> {
+        let shoff = self.u64(0x28)?;
+        let shentsize = self.u16(0x3a)?;
+        let shnum = self.u16(0x3c)?;
+        let shstrndx = self.u16(0x3e)?;
+        if shoff == 0 {
+            return Ok(Vec::new());
+        }
+        let strtab = if shstrndx < shnum {
+            Some(self.u64(shoff + shstrndx * shentsize + 24)?)
+        } else {
+            None
+        };
+        (0..shnum)
+            .map(|i| {
+                let h = shoff + i * shentsize;
+                Ok(Section {
+                    name: match strtab {
+                        Some(strtab) => self.str(strtab + self.u32(h)?)?.to_owned(),
+                        None => String::new(),
+                    },
+                    kind: self.u32(h + 4)? as u32,
+                    flags: self.u64(h + 8)?,
+                    addr: self.u64(h + 16)?,
+                    offset: self.u64(h + 24)?,
+                    size: self.u64(h + 32)?,
+                    link: self.u32(h + 40)? as u32,
+                    entsize: self.u64(h + 56)?,
+                })
+            })
+            .collect()
+    }
+    /// the nul-terminated string at file offset `off`.
+    pub fn str(&self, off: u64) -> Result<&str, Error> {
+        let data = self.data.get(off as usize..).ok_or(Error::Truncated)?;
+        let len = data.iter().position(|&x| x == 0).ok_or(Error::Truncated)?;
+        std::str::from_utf8(&data[..len]).map_err(|_| Error::Truncated)
+    }
+    /// all section headers.
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+    /// all program headers.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+    /// the `PT_LOAD` segment with the lowest `p_vaddr`, which is mapped at the load bias (page aligned).
+    pub fn lowest_load(&self) -> Option<&Segment> {
+        self.segments
+            .iter()
+            .filter(|x| x.kind == PT_LOAD)
+            .min_by_key(|x| x.vaddr)
+    }
+    /// the first section named `name`.
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|x| x.name == name)
+    }
+    /// the content of the section.
+    pub fn section_data(&self, section: &Section) -> Result<&[u8], Error> {
+        self.bytes(section.offset, section.size)
+    }
+    /// the content of the section, decompressed if it is `SHF_COMPRESSED` (zlib or zstd).
+    pub fn section_contents(&self, section: &Section) -> Result<Cow<'_, [u8]>, Error> {
+        let data = self.section_data(section)?;
+        if section.flags & SHF_COMPRESSED == 0 {
+            return Ok(Cow::Borrowed(data));
+        }
+        // Elf64_Chdr: ch_type, ch_reserved, ch_size, ch_addralign
+        let kind = self.u32(section.offset)? as u32;
+        let size = usize::try_from(self.u64(section.offset + 8)?).map_err(|_| Error::Truncated)?;
+        let payload = data.get(24..).ok_or(Error::Truncated)?;
+        decompress(kind, payload, size)
+            .map(Cow::Owned)
+            .map_err(|e| Error::Decompress(section.name.clone(), e))
+    }
+    /// the GNU build-id (`NT_GNU_BUILD_ID` note), if any.
+    pub fn build_id(&self) -> Option<&[u8]> {
+        self.sections
+            .iter()
+            .filter(|x| x.kind == SHT_NOTE)
+            .find_map(|x| self.gnu_note(x, NT_GNU_BUILD_ID))
+    }
+    /// the desc of the first `GNU` note of type `kind` in `section`.
+    fn gnu_note(&self, section: &Section, kind: u32) -> Option<&[u8]> {
+        let align = |x: u64| (x + 3) & !3;
+        let (mut off, end) = (section.offset, section.offset + section.size);
+        while off + 12 <= end {
+            let namesz = self.u32(off).ok()?;
+            let descsz = self.u32(off + 4).ok()?;
+            let name = self.bytes(off + 12, namesz).ok()?;
+            let desc = off + 12 + align(namesz);
+            if self.u32(off + 8).ok()? == kind as u64 && name == b"GNU\0" {
+                return self.bytes(desc, descsz).ok();
+            }
+            off = desc + align(descsz);
+        }
+        None
+    }
+    /// file name and CRC32 of the separate debug file in `.gnu_debuglink`, if any.
+    pub fn debuglink(&self) -> Option<(&str, u32)> {
+        let section = self.section(".gnu_debuglink")?;
+        let name = self.str(section.offset).ok()?;
+        let crc = self
+            .u32(section.offset + (name.len() as u64 + 4) / 4 * 4)
+            .ok()?;
+        Some((name, crc as u32))
+    }
+    /// path and build-id of the dwz supplementary file in `.gnu_debugaltlink`, if any.
+    pub fn debugaltlink(&self) -> Option<(&str, &[u8])> {
+        let section = self.section(".gnu_debugaltlink")?;
+        let name = self.str(section.offset).ok()?;
+        let len = name.len() as u64 + 1;
+        let id = self
+            .bytes(section.offset + len, section.size.checked_sub(len)?)
+            .ok()?;
+        Some((name, id))
+    }
+    /// look up a (mangled) symbol, through `.gnu.hash` first, then `.dynsym` and `.symtab` linearly.
+    pub fn lookup(&self, name: &str) -> Result<Symbol, Error> {
+        for hash in self.sections.iter().filter(|x| x.kind == SHT_GNU_HASH) {
+            if let Some(sym) = self.lookup_gnu_hash(hash, name)? {
+                return Ok(sym);
+            }
+        }
+        for table in self
+            .sections
+            .iter()
+            .filter(|x| x.kind == SHT_DYNSYM || x.kind == SHT_SYMTAB)
+        {
+            for i in 0..table.size / table.entsize.max(24) {
+                if let Some(sym) = self.symbol_at(table, i as u32, name)? {
+                    return Ok(sym);
+                }
+            }
+        }
+        Err(Error::SymbolNotFound(name.to_owned()))
+    }
+    /// the `i`-th symbol in `table`, if its name is `name` and it is defined.
+    fn symbol_at(&self, table: &Section, i: u32, name: &str) -> Result<Option<Symbol>, Error> {
+        let strtab = self
+            .sections
+            .get(table.link as usize)
+            .ok_or(Error::Truncated)?;
+        let sym = table.offset + i as u64 * table.entsize.max(24);
+        let shndx = self.u16(sym + 6)?;
+        if shndx == 0 || self.str(strtab.offset + self.u32(sym)?)? != name {
+            return Ok(None);
+        }
+        Ok(Some(Symbol {
+            value: self.u64(sym + 8)?,
+            size: self.u64(sym + 16)?,
+        }))
+    }
+    fn lookup_gnu_hash(&self, hash: &Section, name: &str) -> Result<Option<Symbol>, Error> {
+        let table = self
+            .sections
+            .get(hash.link as usize)
+            .ok_or(Error::Truncated)?;
+        let h = name
+            .bytes()
+            .fold(5381u32, |h, c| h.wrapping_mul(33).wrapping_add(c as u32));
+        let off = hash.offset;
+        let nbuckets = self.u32(off)?;
+        let symoffset = self.u32(off + 4)?;
+        let bloom_size = self.u32(off + 8)?;
+        let bloom_shift = self.u32(off + 12)?;
+        if nbuckets == 0 || bloom_size == 0 {
+            return Ok(None);
+        }
+        let bloom = off + 16;
+        let word = self.u64(bloom + (h as u64 / 64 % bloom_size) * 8)?;
+        let mask = 1 << (h % 64) | 1 << ((h >> bloom_shift) % 64);
+        if word & mask != mask {
+            return Ok(None);
+        }
+        let buckets = bloom + bloom_size * 8;
+        let chains = buckets + nbuckets * 4;
+        let mut i = self.u32(buckets + (h as u64 % nbuckets) * 4)?;
+        if i == 0 || i < symoffset {
+            return Ok(None);
+        }
+        loop {
+            let h2 = self.u32(chains + (i - symoffset) * 4)? as u32;
+            if h | 1 == h2 | 1
+                && let Some(sym) = self.symbol_at(table, i as u32, name)?
+            {
+                return Ok(Some(sym));
+            }
+            if h2 & 1 == 1 {
+                return Ok(None);
+            }
+            i += 1;
+        }
+    }
+}
+
+/// decompress `data` of `ELFCOMPRESS_*` type `kind`, which should give `size` bytes.
+///
+/// The system libz or libzstd is loaded by `dlopen` on demand, thus no compression library is linked.
+fn decompress(kind: u32, data: &[u8], size: usize) -> Result<Vec<u8>, String> {
+    type Uncompress = unsafe extern "C" fn(*mut u8, *mut c_ulong, *const u8, c_ulong) -> c_int;
+    type ZstdDecompress = unsafe extern "C" fn(*mut u8, usize, *const u8, usize) -> usize;
+    type ZstdIsError = unsafe extern "C" fn(usize) -> c_uint;
+    type ZstdErrorName = unsafe extern "C" fn(usize) -> *const c_char;
+    let mut ret = vec![0u8; size];
+    // SAFETY: the symbols are the documented functions of zlib and zstd, the buffers are valid for their lengths.
+    let len = unsafe {
+        match kind {
+            ELFCOMPRESS_ZLIB => {
+                let uncompress = std::mem::transmute::<*mut c_void, Uncompress>(symbol(
+                    c"libz.so.1",
+                    c"uncompress",
+                )?);
+                let mut len = size as c_ulong;
+                match uncompress(ret.as_mut_ptr(), &mut len, data.as_ptr(), data.len() as _) {
+                    0 => len as usize,
+                    e => return Err(format!("zlib error {e}")),
+                }
+            }
+            ELFCOMPRESS_ZSTD => {
+                let lib = c"libzstd.so.1";
+                let zstd = std::mem::transmute::<*mut c_void, ZstdDecompress>(symbol(
+                    lib,
+                    c"ZSTD_decompress",
+                )?);
+                let is_error =
+                    std::mem::transmute::<*mut c_void, ZstdIsError>(symbol(lib, c"ZSTD_isError")?);
+                let error_name = std::mem::transmute::<*mut c_void, ZstdErrorName>(symbol(
+                    lib,
+                    c"ZSTD_getErrorName",
+                )?);
+                match zstd(ret.as_mut_ptr(), size, data.as_ptr(), data.len()) {
+                    x if is_error(x) != 0 => {
+                        return Err(CStr::from_ptr(error_name(x)).to_string_lossy().into_owned());
+                    }
+                    x => x,
+                }
+            }
+            x => return Err(format!("unknown compression type {x}")),
+        }
+    };
+    match len == size {
+        true => Ok(ret),
+        false => Err(format!("{len} bytes decompressed, {size} expected")),
+    }
+}
+/// `dlsym` of `name` in `lib`, the library stays loaded.
+fn symbol(lib: &CStr, name: &CStr) -> Result<*mut c_void, String> {
+    const RTLD_NOW: c_int = 2;
+    unsafe extern "C" {
+        fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    }
+    let handle = unsafe { dlopen(lib.as_ptr(), RTLD_NOW) };
+    if handle.is_null() {
+        return Err(format!("cannot load {}", lib.to_string_lossy()));
+    }
+    match unsafe { dlsym(handle, name.as_ptr()) } {
+        x if x.is_null() => Err(format!("cannot find {}", name.to_string_lossy())),
+        x => Ok(x),
+    }
+}
+
+/// read `KWin::Workspace::_self`'s offset in libkwin.so (located at `path`).
+pub fn workspace_offset(path: impl AsRef<Path>) -> Result<usize, Error> {
+    Ok(Elf::open(path)?.lookup(WORKSPACE_SELF)?.value as usize)
+}
@@ -0,0 +1,235 @@
+// included in `consts`.
+
+/// one offset update recorded by `try_update_offset` or `rollback_offset`.
+///
+/// The history file holds one line per update, oldest first. The fields are separated by tabs: timestamp, libkwin,
+/// build-id (`-` if none), storage (`executable` or `cache:<path>`), then the old and new `WORKSPACE_OFFSET` and
+/// `POS_OFFSET` in hex (`-` if the cache had none).
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    /// seconds since the unix epoch.
+    pub timestamp: u64,
+    /// path of libkwin the offsets were read from.
+    pub kwin: String,
+    /// build-id of libkwin in lowercase hex, if any.
+    pub build_id: Option<String>,
+    /// where the offsets were saved.
+    pub storage: Storage,
+    /// `WORKSPACE_OFFSET` before the update, for the cache the one cached for libkwin, `None` if there was none.
+    pub old_workspace: Option<usize>,
+    /// `POS_OFFSET` before the update, like `old_workspace`.
+    pub old_pos: Option<usize>,
+    /// `WORKSPACE_OFFSET` after the update, `None` if the cache has none.
+    pub new_workspace: Option<usize>,
+    /// `POS_OFFSET` after the update, like `new_workspace`.
+    pub new_pos: Option<usize>,
+}
+impl HistoryEntry {
+    /// an entry of the update from `report` happened now.
+    pub fn new(kwin: &str, storage: Storage, report: &OffsetUpdate) -> Self {
+        Self {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |x| x.as_secs()),
+            kwin: kwin.to_owned(),
+            build_id: match crate::offsets::Key::of_library(kwin) {
+                Ok(crate::offsets::Key::BuildId(x)) => Some(x),
+                _ => None,
+            },
+            storage,
+            old_workspace: Some(report.old_workspace),
+            old_pos: Some(report.old_pos),
+            new_workspace: Some(report.workspace()),
+            new_pos: Some(report.pos()),
+        }
+    }
+    /// whether any offset differs from the old one.
+    pub fn changed(&self) -> bool {
+        (self.old_workspace, self.old_pos) != (self.new_workspace, self.new_pos)
+    }
+    fn parse(line: &str) -> Option<Self> {
+        let hex = |x: &str| match x {
+            "-" => Some(None),
+            x => usize::from_str_radix(x.strip_prefix("0x")?, 16)
+                .ok()
+                .map(Some),
+        };
+        let mut fields = line.split('\t');
+        let mut next = || fields.next();
+        let ret = Self {
+            timestamp: next()?.parse().ok()?,
+            kwin: next()?.to_owned(),
+            build_id: Some(next()?).filter(|x| *x != "-").map(ToOwned::to_owned),
+            storage: match next()? {
+                "executable" => Storage::Executable,
+                x => Storage::Cache(x.strip_prefix("cache:")?.into()),
+            },
+            old_workspace: hex(next()?)?,
+            old_pos: hex(next()?)?,
+            new_workspace: hex(next()?)?,
+            new_pos: hex(next()?)?,
+        };
+        next().is_none().then_some(ret)
+    }
+}
+/// render a line of the history file.
+impl std::fmt::Display for HistoryEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}\t",
+            self.timestamp,
+            self.kwin,
+            self.build_id.as_deref().unwrap_or("-")
+        )?;
+        match &self.storage {
+            Storage::Executable => write!(f, "executable")?,
+            Storage::Cache(x) => write!(f, "cache:{}", x.display())?,
+        }
+        for x in [
+            self.old_workspace,
+            self.old_pos,
+            self.new_workspace,
+            self.new_pos,
+        ] {
+            match x {
+                Some(x) => write!(f, "\t0x{x:x}")?,
+                None => write!(f, "\t-")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// append `entry` to the history file at `path`, the parent directories are created if needed.
+pub fn record_offset_history(path: &Path, entry: &HistoryEntry) -> Result<(), UpdateError> {
+    use crate::offsets::Error;
+    use std::io::Write;
+    if entry.kwin.contains(['\t', '\n']) {
+        return Err(UpdateError::History(Error::Parse(
+            0,
+            format!("path of libkwin {:?} is not recordable", entry.kwin),
+        )));
+    }
+    let write = || -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{entry}")?;
+        file.sync_all()
+    };
+    write().map_err(|e| UpdateError::History(Error::Io(e)))
+}
+
+/// every recorded update in `offsets::history_path()`, oldest first. A missing file gives an empty history.
+pub fn list_offset_history() -> Result<Vec<HistoryEntry>, UpdateError> {
+    list_offset_history_at(&crate::offsets::history_path())
+}
+
+/// like `list_offset_history`, with the history file at `path`.
+pub fn list_offset_history_at(path: &Path) -> Result<Vec<HistoryEntry>, UpdateError> {
+    use crate::offsets::Error;
+    let text = match fs::read_to_string(path) {
+        Ok(x) => x,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(UpdateError::History(Error::Io(e))),
+    };
+    text.lines()
+        .enumerate()
+        .filter(|x| !x.1.trim().is_empty() && !x.1.starts_with('#'))
+        .map(|(i, line)| {
+            HistoryEntry::parse(line).ok_or_else(|| {
+                UpdateError::History(Error::Parse(i + 1, "malformed history entry".into()))
+            })
+        })
+        .collect()
+}
+
+/// restore the offsets from before the `n`-th latest update (0 is the latest), into the storage it was saved in.
+///
+/// Entries saved into the executable are restored into the current executable. For the cache, the entry of libkwin
+/// is restored as it was, i.e., removed if the update created it. The rollback is recorded as well,
+/// thus `rollback_offset(0)` twice gets back to where it started.
+/// ```no_run
+/// #[cfg(feature = "update-offset")]
+/// {
+///     use kwin_mouse_loc::consts::{list_offset_history, rollback_offset};
+///     for entry in list_offset_history().unwrap() {
+///         println!("{entry}");
+///     }
+///     println!("{}", rollback_offset(0).unwrap());
+/// }
+/// ```
+pub fn rollback_offset(n: usize) -> Result<OffsetUpdate, UpdateError> {
+    rollback_offset_at(&crate::offsets::history_path(), n)
+}
+
+/// like `rollback_offset`, with the history file at `path`.
+pub fn rollback_offset_at(path: &Path, n: usize) -> Result<OffsetUpdate, UpdateError> {
+    // SAFETY: the statics are only written in `apply`, as `try_update_offset` does.
+    let current = unsafe { (WORKSPACE_OFFSET, POS_OFFSET) };
+    rollback_offset_with(path, n, current, |x| unsafe {
+        WORKSPACE_OFFSET = x.workspace();
+        POS_OFFSET = x.pos();
+    })
+}
+
+/// `rollback_offset_at` without touching the statics: `current` are the offsets in use, and `apply` is called
+/// once the storage is restored, before the rollback is recorded.
+pub(crate) fn rollback_offset_with(
+    path: &Path,
+    n: usize,
+    current: (usize, usize),
+    apply: impl FnOnce(&OffsetUpdate),
+) -> Result<OffsetUpdate, UpdateError> {
+    let history = list_offset_history_at(path)?;
+    let entry = history
+        .len()
+        .checked_sub(n + 1)
+        .map(|i| &history[i])
+        .ok_or(UpdateError::NoHistory(n))?;
+    let (old_workspace, old_pos) = current;
+    let cached = match &entry.storage {
+        Storage::Executable => {
+            for (val, item) in [
+                (entry.old_pos, Offset::Pos),
+                (entry.old_workspace, Offset::Kwin),
+            ] {
+                let Some(val) = val else { continue };
+                unsafe { try_save_offset(val, item) }.map_err(UpdateError::Save)?;
+            }
+            None
+        }
+        Storage::Cache(cache) => {
+            // the recorded build-id, since libkwin might have been upgraded again.
+            let key = match &entry.build_id {
+                Some(x) => crate::offsets::Key::BuildId(x.clone()),
+                None => crate::offsets::Key::of_library(&entry.kwin).map_err(UpdateError::Elf)?,
+            };
+            let cached = cached_offsets(cache, &key)?;
+            restore_cached_offsets(cache, key, entry.old_workspace, entry.old_pos)?;
+            Some(cached)
+        }
+    };
+    let report = OffsetUpdate {
+        old_workspace,
+        old_pos,
+        new_workspace: entry.old_workspace,
+        new_pos: entry.old_pos,
+        pos_skipped: None,
+        validation: None,
+        written: true,
+    };
+    apply(&report);
+    let mut record = HistoryEntry {
+        build_id: entry.build_id.clone(),
+        ..HistoryEntry::new(&entry.kwin, entry.storage.clone(), &report)
+    };
+    if let Some((workspace, pos)) = cached {
+        (record.old_workspace, record.old_pos) = (workspace, pos);
+        (record.new_workspace, record.new_pos) = (entry.old_workspace, entry.old_pos);
+    }
+    record_offset_history(path, &record)?;
+    Ok(report)
+}
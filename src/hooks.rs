@@ -0,0 +1,290 @@
+// included in `consts`.
+
+/// package managers that could run `update-offset` after kwin is upgraded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PackageManager {
+    /// a pacman hook in `/etc/pacman.d/hooks`, for Arch.
+    Pacman,
+    /// a `DPkg::Post-Invoke` script in `/etc/apt/apt.conf.d`, for Debian and Ubuntu.
+    Dpkg,
+    /// an action of the dnf4 `post-transaction-actions` plugin, for Fedora 40 and older.
+    Rpm,
+    /// an action of the libdnf5 `actions` plugin, for Fedora 41 and newer.
+    Dnf5,
+}
+impl PackageManager {
+    /// every supported package manager.
+    pub const ALL: [Self; 4] = [Self::Pacman, Self::Dpkg, Self::Rpm, Self::Dnf5];
+    /// name used by the `install-hooks` subcommand.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Pacman => "pacman",
+            Self::Dpkg => "dpkg",
+            Self::Rpm => "rpm",
+            Self::Dnf5 => "dnf5",
+        }
+    }
+    /// the files of libkwin the trigger watches.
+    ///
+    /// The package shipping libkwin differs between distros (and releases), thus the triggers match the library
+    /// itself instead of a package name.
+    pub fn libkwin_files(&self) -> &'static str {
+        match self {
+            Self::Pacman => "usr/lib/libkwin.so*",
+            Self::Dpkg => "/usr/lib/*/libkwin.so.* /usr/lib/libkwin.so.*",
+            Self::Rpm | Self::Dnf5 => "/usr/lib64/libkwin.so.*",
+        }
+    }
+    /// the package managers (with the plugin running the trigger, for dnf) installed under `root`.
+    pub fn detect(root: &Path) -> Vec<Self> {
+        let exists = |x: &str| root.join(x).exists();
+        Self::ALL
+            .into_iter()
+            .filter(|x| match x {
+                Self::Pacman => exists("etc/pacman.conf"),
+                Self::Dpkg => exists("var/lib/dpkg/status"),
+                Self::Rpm => exists("etc/dnf/plugins/post-transaction-actions.conf"),
+                Self::Dnf5 => exists("etc/dnf/libdnf5-plugins/actions.conf"),
+            })
+            .collect()
+    }
+}
+impl std::str::FromStr for PackageManager {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|x| x.name() == s)
+            .ok_or_else(|| {
+                format!("unknown package manager {s:?}, expect pacman, dpkg, rpm or dnf5")
+            })
+    }
+}
+
+/// where the dpkg trigger records the state of libkwin the offsets are updated for.
+const DPKG_STAMPS: &str = "/var/cache/kwin-mouse-loc";
+
+/// a post-upgrade trigger running `{exe} update-offset` when libkwin is installed or upgraded.
+///
+/// The libkwin to read is found by `find_libkwin` when the trigger runs, unless set by `Hook::kwin`.
+///
+/// ```no_run
+/// #[cfg(feature = "update-offset")]
+/// {
+///     use kwin_mouse_loc::consts::{Hook, PackageManager};
+///     let exe = std::env::current_exe().unwrap();
+///     for manager in PackageManager::detect("/".as_ref()) {
+///         println!("{}", Hook::new(manager, &exe).install("/".as_ref()).unwrap().display());
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hook {
+    manager: PackageManager,
+    exe: std::path::PathBuf,
+    kwin: Option<std::path::PathBuf>,
+}
+impl Hook {
+    /// a trigger of `manager` for the executable at `exe`, which should call `subcommand` (or `update_offset`).
+    pub fn new(manager: PackageManager, exe: impl AsRef<Path>) -> Self {
+        Self {
+            manager,
+            exe: exe.as_ref().to_owned(),
+            kwin: None,
+        }
+    }
+    /// pass `--kwin {kwin}` to `update-offset`.
+    pub fn kwin(mut self, kwin: impl AsRef<Path>) -> Self {
+        self.kwin = Some(kwin.as_ref().to_owned());
+        self
+    }
+    fn name(&self) -> std::borrow::Cow<'_, str> {
+        self.exe
+            .file_name()
+            .map_or("exe".into(), |x| x.to_string_lossy())
+    }
+    /// the trigger file, relative to the root directory.
+    pub fn path(&self) -> std::path::PathBuf {
+        let name = self.name();
+        match self.manager {
+            PackageManager::Pacman => format!("etc/pacman.d/hooks/kwin-mouse-loc-{name}.hook"),
+            PackageManager::Dpkg => format!("etc/apt/apt.conf.d/99kwin-mouse-loc-{name}"),
+            PackageManager::Rpm => {
+                format!("etc/dnf/plugins/post-transaction-actions.d/kwin-mouse-loc-{name}.action")
+            }
+            PackageManager::Dnf5 => {
+                format!("etc/dnf/libdnf5-plugins/actions.d/kwin-mouse-loc-{name}.actions")
+            }
+        }
+        .into()
+    }
+    /// content of the trigger file, fails if the paths cannot be written unquoted.
+    pub fn render(&self) -> std::io::Result<String> {
+        fn unquoted(path: &Path) -> std::io::Result<&str> {
+            path.to_str()
+                .filter(|x| {
+                    path.is_absolute()
+                        && !x.contains(|c: char| c.is_whitespace() || "\"'\\;:$`*?".contains(c))
+                })
+                .ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!("{} cannot be used in a hook", path.display()),
+                    )
+                })
+        }
+        let command = match &self.kwin {
+            Some(kwin) => format!(
+                "{} update-offset --kwin {}",
+                unquoted(&self.exe)?,
+                unquoted(kwin)?
+            ),
+            None => format!("{} update-offset", unquoted(&self.exe)?),
+        };
+        let files = self.manager.libkwin_files();
+        Ok(match self.manager {
+            PackageManager::Pacman => format!(
+                "[Trigger]\nOperation = Install\nOperation = Upgrade\nType = Path\nTarget = {files}\n\n\
+                 [Action]\nDescription = Updating offsets of {exe}\nWhen = PostTransaction\nExec = {command}\n",
+                exe = self.exe.display(),
+            ),
+            // apt hooks have no file trigger, the recorded inode, size and mtime of libkwin limit the update to
+            // upgrades of libkwin.
+            PackageManager::Dpkg => format!(
+                "DPkg::Post-Invoke {{ \"v=$(stat -L -c %i.%s.%Y {files} 2>/dev/null | paste -sd,); \
+                 s={stamp}; if [ x$v != x ] && [ x$v != x$(cat $s 2>/dev/null) ]; \
+                 then {command} && mkdir -p {dir} && echo $v > $s; fi; true\"; }};\n",
+                dir = DPKG_STAMPS,
+                stamp = format_args!("{DPKG_STAMPS}/{}.libkwin", self.name()),
+            ),
+            PackageManager::Rpm => format!("{files}:in:{command}\n"),
+            PackageManager::Dnf5 => format!("post_transaction:{files}:in::{command}\n"),
+        })
+    }
+    /// write the trigger under `root` (`/` for the running system), the parent directories are created if needed.
+    pub fn install(&self, root: &Path) -> std::io::Result<std::path::PathBuf> {
+        let content = self.render()?;
+        let path = root.join(self.path());
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+    /// remove the trigger under `root`, returns whether it existed.
+    pub fn uninstall(&self, root: &Path) -> std::io::Result<bool> {
+        match fs::remove_file(root.join(self.path())) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// run the command given by `std::env::args()` and exit, or return if there is no known command.
+///
+/// Call it at the start of `main` to support the commands used by `Hook`:
+/// ```text
+/// update-offset [--kwin PATH]                                        update and save the offsets, see `try_update_offset`
+/// install-hooks [--root DIR] [--kwin PATH] [pacman|dpkg|rpm|dnf5]...  install triggers of the current executable
+/// uninstall-hooks [--root DIR] [pacman|dpkg|rpm|dnf5]...              remove them
+/// ```
+/// Without `--kwin`, `update-offset` reads the libkwin found by `find_libkwin`, and `POS_OFFSET` is read from its
+/// debug info. Without package managers, the ones detected by `PackageManager::detect` are used.
+pub fn subcommand() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if let Some(code) = run_subcommand(&args) {
+        std::process::exit(code)
+    }
+}
+
+/// like `subcommand`, returns the exit code instead of exiting, `None` if `args` is not a known command.
+pub fn run_subcommand(args: &[String]) -> Option<i32> {
+    let (command, args) = args.split_first()?;
+    let install = match command.as_str() {
+        "update-offset" => {
+            let options = match args {
+                [] => UpdateOptions::new(),
+                [flag, kwin] if flag == "--kwin" => UpdateOptions::new().kwin(Some(kwin)),
+                _ => {
+                    eprintln!("usage: update-offset [--kwin PATH]");
+                    return Some(2);
+                }
+            };
+            return Some(match try_update_offset(&options) {
+                Ok(report) => {
+                    println!("{report}");
+                    0
+                }
+                Err(e) => {
+                    eprintln!("update failed: {e}");
+                    1
+                }
+            });
+        }
+        "install-hooks" => true,
+        "uninstall-hooks" => false,
+        _ => return None,
+    };
+    let mut root = std::path::PathBuf::from("/");
+    let mut kwin = None;
+    let mut managers = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--root" {
+            let Some(x) = args.next() else {
+                eprintln!("--root requires a directory");
+                return Some(2);
+            };
+            root = x.into();
+            continue;
+        }
+        if arg == "--kwin" && install {
+            let Some(x) = args.next() else {
+                eprintln!("--kwin requires a path");
+                return Some(2);
+            };
+            kwin = Some(x);
+            continue;
+        }
+        match arg.parse() {
+            Ok(x) => managers.push(x),
+            Err(e) => {
+                eprintln!("{e}");
+                return Some(2);
+            }
+        }
+    }
+    if managers.is_empty() {
+        managers = PackageManager::detect(&root);
+    }
+    let exe = match env::current_exe() {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("cannot find current exe: {e}");
+            return Some(1);
+        }
+    };
+    let mut code = 0;
+    for manager in managers {
+        let hook = match kwin {
+            Some(kwin) => Hook::new(manager, &exe).kwin(kwin),
+            None => Hook::new(manager, &exe),
+        };
+        let path = root.join(hook.path());
+        let result = if install {
+            hook.install(&root).map(|_| "installed")
+        } else {
+            hook.uninstall(&root)
+                .map(|x| if x { "removed" } else { "not installed" })
+        };
+        match result {
+            Ok(x) => println!("{}: {x}", path.display()),
+            Err(e) => {
+                eprintln!("{}: {e}", path.display());
+                code = 1;
+            }
+        }
+    }
+    Some(code)
+}
@@ -18,6 +18,8 @@
 //!
 //! (all the tests require root permissions, without root permissions, the program might failed to execute)
 //!
+//! This crate never changes its credentials implicitly, for setuid executables, call `privilege::escalate()` before searching kwin_wayland.
+//!
 //! ```no_run
 //! use kwin_mouse_loc::pointer::Workspace;
 //! fn main(){
@@ -32,7 +34,13 @@
 //! }
 //! ```
 //! # Features
+//! `async`         : a `Stream`-like position poller driven by `timerfd` and `epoll`, requires no runtime.
+//!
 //! `docgen-detect` : for publish (and doc generation without process header file) thus enabled by default. For normal use, just disable it.
+//!                   Dummy offsets are only generated on docs.rs (or with `KWIN_MOUSE_LOC_DUMMY=1`), otherwise missing headers fail the build.
+//!
+//! The headers and libkwin are discovered by pkg-config and the CMake config files of KWin, KF6 and Qt6,
+//! `KWIN_INCLUDE` (one directory per line) and `KWIN_LIBRARY` override them.
 //!
 //! `keyboard`      : requires `uinput`, allow using keyboard commands
 //!
@@ -47,8 +55,22 @@
 #![cfg_attr(doc, feature(doc_cfg))]
 #[cfg(feature = "uinput")]
 pub mod device;
+/// a small DWARF reader, used to find the offset of `focusMousePos` from debug info.
+pub mod dwarf;
+/// a small ELF64 reader, used to find `KWin::Workspace::_self` without `readelf`.
+pub mod elf;
+/// versioned offsets of several libkwin builds, keyed by build-id or version.
+pub mod offsets;
+/// checks (and explicitly acquires) the privileges needed to read kwin_wayland's memory.
+pub mod privilege;
+/// kwin_wayland discovery by scanning `/proc`, without relying on `ps`.
+pub mod procfs;
+/// runtime-free position stream driven by `timerfd` and `epoll`.
+#[cfg_attr(doc, doc(cfg(feature = "async")))]
+#[cfg(any(doc, feature = "async"))]
+pub mod stream;
 /// Some constants, which could be updated if feature `update-offset` is set.
-/// It is worth mention that, the `update-offset` feature highly relies on `readelf` executable, and use the following sections:
+/// It is worth mention that, the `update-offset` feature patches the following sections, located by the ELF section headers of the executable:
 /// ```text
 /// .kwin.mouse.loc.pos
 /// .kwin.mouse.loc.kwin
@@ -57,62 +79,289 @@ pub mod device;
 /// Adding other variable into such section may damage the executable.
 pub mod consts {
     include!(concat!(env!("OUT_DIR"), "/consts.rs"));
+    /// how the build script computed the compiled-in offsets.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub enum OffsetSource {
+        /// read from the debug info of libkwin.
+        Dwarf,
+        /// computed by bindgen from the headers of kwin.
+        Bindgen,
+        /// dummy values generated by `docgen-detect` for documents, never rely on them.
+        Dummy,
+    }
+    /// what the build script knew about libkwin, see `BUILD_INFO`.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct BuildInfo {
+        /// path of libkwin the offsets were read from.
+        pub library: &'static str,
+        /// version suffix of the real file name of libkwin, e.g., `6.1.4`.
+        pub version: Option<&'static str>,
+        /// build-id of libkwin in lowercase hex.
+        pub build_id: Option<&'static str>,
+        /// how the offsets were computed.
+        pub source: OffsetSource,
+        /// include directories given to bindgen.
+        pub includes: &'static [&'static str],
+        /// size of `KWin::Workspace`, if known.
+        pub workspace_size: Option<usize>,
+        /// the compiled-in `WORKSPACE_OFFSET`.
+        pub workspace_offset: usize,
+        /// the compiled-in `POS_OFFSET`.
+        pub pos_offset: usize,
+    }
+    impl BuildInfo {
+        /// whether the offsets were made up for documents.
+        pub fn dummy(&self) -> bool {
+            self.source == OffsetSource::Dummy
+        }
+        /// whether the compiled-in offsets apply to a libkwin with `build_id`, `None` if either build-id is unknown.
+        pub fn matches(&self, build_id: Option<&[u8]>) -> Option<bool> {
+            let (built, mapped) = (self.build_id?, build_id?);
+            Some(
+                built.len() == mapped.len() * 2
+                    && mapped
+                        .iter()
+                        .zip(built.as_bytes().chunks(2))
+                        .all(|(x, h)| format!("{x:02x}").as_bytes() == h),
+            )
+        }
+        /// whether `WORKSPACE_OFFSET` and `POS_OFFSET` still hold the compiled-in values, i.e., not updated by
+        /// `update-offset` since.
+        pub fn compiled(&self) -> bool {
+            // SAFETY: only read.
+            unsafe { (WORKSPACE_OFFSET, POS_OFFSET) == (self.workspace_offset, self.pos_offset) }
+        }
+    }
+    impl std::fmt::Display for BuildInfo {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "{} (version {}, build-id {}), {:?} offsets: WORKSPACE_OFFSET = 0x{:06x}, POS_OFFSET = 0x{:04x}",
+                self.library,
+                self.version.unwrap_or("unknown"),
+                self.build_id.unwrap_or("unknown"),
+                self.source,
+                self.workspace_offset,
+                self.pos_offset
+            )?;
+            if let Some(x) = self.workspace_size {
+                write!(f, ", size of KWin::Workspace = {x}")?;
+            }
+            Ok(())
+        }
+    }
+    /// read the offset of `focusMousePos` in `KWin::Workspace` from the debug info of libkwin.so (located at `path`).
+    ///
+    /// The debug file is found by build-id or `.gnu_debuglink` under `/usr/lib/debug`, see `dwarf::find_debug_file`.
+    /// Compressed sections (zlib or zstd) and dwz supplementary files (`.gnu_debugaltlink`) are read as well, as
+    /// shipped by Debian dbgsym and Fedora debuginfo packages.
+    pub fn offset_pos_from_dwarf(path: &str) -> Result<usize, crate::dwarf::Error> {
+        crate::dwarf::focus_mouse_pos_offset(path)
+    }
     #[cfg_attr(doc, doc(cfg(feature = "update-offset")))]
     #[cfg(any(doc, feature = "update-offset"))]
     include!("update_offset.rs");
+    #[cfg_attr(doc, doc(cfg(feature = "update-offset")))]
+    #[cfg(any(doc, feature = "update-offset"))]
+    include!("try_update.rs");
+    #[cfg_attr(doc, doc(cfg(feature = "update-offset")))]
+    #[cfg(any(doc, feature = "update-offset"))]
+    include!("patch.rs");
+    #[cfg_attr(doc, doc(cfg(feature = "update-offset")))]
+    #[cfg(any(doc, feature = "update-offset"))]
+    include!("history.rs");
+    #[cfg_attr(doc, doc(cfg(feature = "update-offset")))]
+    #[cfg(any(doc, feature = "update-offset"))]
+    include!("hooks.rs");
+    #[cfg_attr(doc, doc(cfg(feature = "uinput")))]
+    #[cfg(feature = "uinput")]
+    include!("calibrate.rs");
 }
 
 /// pointer of kwin workspace and its cursor's position
 pub mod pointer {
-    use crate::consts::*;
+    use crate::{
+        consts::*,
+        elf,
+        privilege::{Access, Report},
+        procfs::{self, Filter, Library, Scanner},
+    };
     use libc::{iovec, process_vm_readv};
-    use std::{ffi::c_void, fmt::Display, fs::File, io::Read, process::Command, ptr};
+    use std::{ffi::c_void, fmt::Display, fs, os::unix::fs::MetadataExt, path::Path, ptr};
+    /// errors that might occur while locating kwin_wayland or reading its memory.
+    #[derive(Debug)]
+    #[non_exhaustive]
+    pub enum Error {
+        /// cannot find a running kwin_wayland.
+        PidNotFound,
+        /// `/proc` cannot be enumerated.
+        ProcUnreadable(std::io::Error),
+        /// `/proc/{pid}/maps` cannot be read (require permissions?)
+        MapsUnreadable(std::io::Error),
+        /// the process does not map libkwin.so (is it really kwin_wayland?)
+        LibkwinNotMapped,
+        /// more than one mapped file matches the library, with their paths.
+        AmbiguousLibkwin(Vec<String>),
+        /// `process_vm_readv` returns fewer bytes than expected.
+        ShortRead {
+            /// bytes requested
+            expected: usize,
+            /// bytes actually read
+            read: usize,
+        },
+        /// the current credentials cannot read kwin_wayland's memory.
+        PermissionDenied(Box<Report>),
+        /// a syscall failed, with the `errno` it set.
+        Errno(i32),
+        /// the pointer `KWin::Workspace::_self` is null, kwin might still be initializing.
+        NullWorkspace,
+        /// the compiled-in offsets are for another build of libkwin, with both build-ids.
+        BuildMismatch {
+            /// build-id in `BUILD_INFO`
+            built: String,
+            /// build-id of the mapped libkwin
+            mapped: String,
+        },
+        /// libkwin.so cannot be parsed, or the symbol is missing.
+        Elf(elf::Error),
+    }
+    impl Error {
+        /// capture the current `errno`.
+        pub fn last_errno() -> Self {
+            Self::Errno(std::io::Error::last_os_error().raw_os_error().unwrap_or(0))
+        }
+    }
+    impl Display for Error {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::PidNotFound => write!(f, "failed to find kwin_wayland session"),
+                Self::ProcUnreadable(e) => write!(f, "cannot enumerate programs: {e}"),
+                Self::MapsUnreadable(e) => {
+                    write!(f, "cannot read maps (require permissions?): {e}")
+                }
+                Self::LibkwinNotMapped => write!(
+                    f,
+                    "program does not load libkwin.so (is it really kwin_wayland?)"
+                ),
+                Self::ShortRead { expected, read } => {
+                    write!(f, "unknown bytes readed: {read} (expected {expected})")
+                }
+                Self::Errno(e) => write!(
+                    f,
+                    "failed with errno {e}: {}",
+                    std::io::Error::from_raw_os_error(*e)
+                ),
+                Self::PermissionDenied(r) => write!(f, "permission denied ({r})"),
+                Self::AmbiguousLibkwin(x) => write!(f, "more than one libkwin.so is mapped: {x:?}"),
+                Self::NullWorkspace => write!(f, "KWin::Workspace::_self is null"),
+                Self::BuildMismatch { built, mapped } => write!(
+                    f,
+                    "offsets are compiled for libkwin {built}, but {mapped} is mapped (run update-offset?)"
+                ),
+                Self::Elf(e) => write!(f, "{e}"),
+            }
+        }
+    }
+    impl From<elf::Error> for Error {
+        fn from(e: elf::Error) -> Self {
+            Self::Elf(e)
+        }
+    }
+    impl std::error::Error for Error {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match self {
+                Self::MapsUnreadable(e) | Self::ProcUnreadable(e) => Some(e),
+                Self::Elf(e) => Some(e),
+                _ => None,
+            }
+        }
+    }
+    /// read `len` bytes at `remote` from process `pid` into `local`.
+    fn read_remote(
+        pid: KWinPid,
+        local: *mut c_void,
+        remote: *mut c_void,
+        len: usize,
+    ) -> Result<(), Error> {
+        let local = iovec {
+            iov_base: local,
+            iov_len: len,
+        };
+        let remote = iovec {
+            iov_base: remote,
+            iov_len: len,
+        };
+        // SAFETY: `local` is provided by the caller and is writable for `len` bytes,
+        //         the remote side is checked by the kernel.
+        match unsafe { process_vm_readv(pid.0, &local, 1, &remote, 1, 0) } {
+            -1 => Err(Error::last_errno()),
+            x if x as usize == len => Ok(()),
+            x => Err(Error::ShortRead {
+                expected: len,
+                read: x as usize,
+            }),
+        }
+    }
     /// PID of kwin_wayland.
     /// SAFETY: users should ensure this is the pid of kwin_wayland, and this PID is valid before this program exited.
     #[derive(Clone, Copy, Eq, PartialEq)]
     pub struct KWinPid(i32);
     impl KWinPid {
         /// SAFETY: users should ensure this is the pid of kwin_wayland, and this PID is valid before this program exited.
+        ///
+        /// No privilege is acquired here, use `privilege::escalate` explicitly if needed.
         pub unsafe fn from(i: i32) -> Self {
-            unsafe {
-                if libc::getuid() != 0 {
-                    // if is not root
-                    if libc::setuid(0) != 0 {
-                        // if cannot be root
-                        panic!("cannot set uid to 0, further code could not be executed.")
-                    }
-                }
-            }
             Self(i)
         }
+        /// like `from`, but returns `Error::PermissionDenied` if `process_vm_readv` on `i` will fail with the current credentials.
+        ///
         /// SAFETY: users should ensure this is the pid of kwin_wayland, and this PID is valid before this program exited.
-        pub unsafe fn search(all_user: bool) -> Self {
-            unsafe {
-                Self::from(
-                    String::from_utf8_lossy(
-                        &Command::new("ps")
-                            .arg(if all_user { "ax" } else { "x" }) // "a" is needed since there might not be a wayland window running by root.
-                            .output()
-                            .expect("cannot enumerate programs")
-                            .stdout,
-                    )
-                    .lines()
-                    .filter(|x| x.contains("/kwin_wayland "))
-                    .next()
-                    .expect("failed to find kwin_wayland session")
-                    .trim()
-                    .split_once(' ')
-                    .expect("cannot parse `ps`'s output")
-                    .0
-                    .parse()
-                    .expect("cannot parse the pid"),
-                )
+        pub unsafe fn try_from(i: i32) -> Result<Self, Error> {
+            let report = Report::check(i);
+            match report.access() {
+                Access::Granted => Ok(Self(i)),
+                _ => Err(Error::PermissionDenied(Box::new(report))),
             }
         }
+        /// the raw pid.
+        pub fn pid(&self) -> i32 {
+            self.0
+        }
+        /// SAFETY: users should ensure this is the pid of kwin_wayland, and this PID is valid before this program exited.
+        pub unsafe fn search(all_user: bool) -> Self {
+            unsafe { Self::try_search(all_user) }.unwrap_or_else(|e| panic!("{e}"))
+        }
+        /// fallible version of `search`, scanning `/proc` natively.
+        ///
+        /// If `all_user` is false, only kwin_wayland owned by the current (effective) uid is considered.
+        ///
+        /// SAFETY: users should ensure this is the pid of kwin_wayland, and this PID is valid before this program exited.
+        pub unsafe fn try_search(all_user: bool) -> Result<Self, Error> {
+            let scanner = Scanner::new();
+            // "all_user" is needed since there might not be a wayland window running by root.
+            let scanner = if all_user {
+                scanner
+            } else {
+                scanner.filter(Filter::Uid(unsafe { libc::geteuid() }))
+            };
+            unsafe { Self::try_search_with(&scanner) }
+        }
+        /// pick the first (smallest pid) candidate found by `scanner`.
+        ///
+        /// SAFETY: users should ensure this is the pid of kwin_wayland, and this PID is valid before this program exited.
+        pub unsafe fn try_search_with(scanner: &Scanner) -> Result<Self, Error> {
+            let candidate = scanner
+                .scan()
+                .map_err(Error::ProcUnreadable)?
+                .into_iter()
+                .next()
+                .ok_or(Error::PidNotFound)?;
+            unsafe { Self::try_from(candidate.pid) }
+        }
     }
     #[derive(Eq, PartialEq)]
-    /// pointer of workspace
-    pub struct Workspace(KWinPid, *mut c_void);
+    /// pointer of workspace, with the offset of `focusMousePos` used by `get_mouse`.
+    pub struct Workspace(KWinPid, *mut c_void, usize);
     impl Workspace {
         /// Automatically create a workspace pointer with default offset (might be wrong!) and automatically detected kwin_wayland (may also wrong!).
         /// Use for test and demo only.
@@ -120,96 +369,182 @@ pub mod pointer {
         /// SAFETY: Ensure the WORKSPACE_OFFSET is correct.
         ///
         /// require root permissions to calculate the workspace's offset. If the root permission is provided by
+        ///
+        /// The offsets cached for the mapped libkwin in `offsets::cache_path()` are preferred, see `try_new`.
+        /// Panics with `Error::BuildMismatch` if the compiled-in offsets are used, but for another build of the mapped libkwin.
         pub unsafe fn new(search_all_user: bool) -> Self {
-            unsafe { Self::get(KWinPid::search(search_all_user), WORKSPACE_OFFSET) }
+            unsafe { Self::try_new(search_all_user) }.unwrap_or_else(|e| panic!("{e}"))
+        }
+        /// fallible version of `new`.
+        ///
+        /// The offsets cached for the mapped libkwin in `offsets::cache_path()` are preferred, see `offsets::Database::load_cache`.
+        /// A cache that cannot be read or parsed is ignored.
+        ///
+        /// SAFETY: Ensure the WORKSPACE_OFFSET is correct.
+        pub unsafe fn try_new(search_all_user: bool) -> Result<Self, Error> {
+            let pid = unsafe { KWinPid::try_search(search_all_user)? };
+            Self::try_get_cached(pid, &Library::default())
+        }
+        /// like `try_get_with`, with the offsets cached for the mapped libkwin, or the statics if none is cached.
+        ///
+        /// The statics are refused if they hold the compiled-in offsets for another build of the mapped libkwin.
+        pub(crate) fn try_get_cached(pid: KWinPid, library: &Library) -> Result<Self, Error> {
+            let (base, elf, mapping) = Self::load_libkwin(pid, library)?;
+            let cache = crate::offsets::Database::load_cache().unwrap_or_default();
+            // SAFETY: the statics are only written by `update_offset`.
+            let (workspace_offset, pos_offset) = unsafe { (WORKSPACE_OFFSET, POS_OFFSET) };
+            let (workspace_offset, pos_offset) =
+                match cache.lookup(elf.build_id(), mapping.version()) {
+                    Some(entry) if let Some(x) = entry.workspace => {
+                        (x, entry.pos.unwrap_or(pos_offset))
+                    }
+                    _ => {
+                        Self::check_build(&elf)?;
+                        (workspace_offset, pos_offset)
+                    }
+                };
+            let this = unsafe { (base as *mut c_void).byte_add(workspace_offset) };
+            Ok(Self(pid, this, pos_offset))
         }
         /// get workspace from kwin_wayland, the pid should met kwin_wayland's pid, otherwise I cannot tell what happens.
         /// since it relys on reading "/proc/{pid}/maps", root access might be needed.
         ///
-        /// It will use an offset that calculated in compile-time, if runtime detect is needed, using `get_with_readelf` instead (executable`readelf` should in enviroment `$PATH`)
+        /// It will use an offset that calculated in compile-time, if runtime detect is needed, using `get_offset` to read it from libkwin.so instead.
         ///
         /// require root permissions to calculate the workspace's offset.
         pub fn get(pid: KWinPid, workspace_offset: usize) -> Self {
-            let mut buffer = String::new();
-
+            Self::try_get(pid, workspace_offset).unwrap_or_else(|e| panic!("{e}"))
+        }
+        /// fallible version of `get`.
+        pub fn try_get(pid: KWinPid, workspace_offset: usize) -> Result<Self, Error> {
+            Self::try_get_with(pid, workspace_offset, &Library::default())
+        }
+        /// like `try_get`, but the mapped libkwin is selected by `library` (name, exact path or inode).
+        ///
+        /// The load bias is computed from the lowest `PT_LOAD` segment of the library, thus the
+        /// permissions and order of its mappings do not matter.
+        pub fn try_get_with(
+            pid: KWinPid,
+            workspace_offset: usize,
+            library: &Library,
+        ) -> Result<Self, Error> {
+            let (base, _, _) = Self::load_libkwin(pid, library)?;
+            let base = base as *mut c_void;
+            let ret = unsafe { base.byte_add(workspace_offset) };
+            Ok(Self(pid, ret, unsafe { POS_OFFSET }))
+        }
+        /// `Error::BuildMismatch` if the statics still hold the compiled-in offsets, built for another libkwin than `elf`.
+        ///
+        /// Offsets updated since the build are trusted.
+        fn check_build(elf: &elf::Elf) -> Result<(), Error> {
+            match BUILD_INFO.compiled() && BUILD_INFO.matches(elf.build_id()) == Some(false) {
+                true => Err(Error::BuildMismatch {
+                    built: BUILD_INFO.build_id.unwrap_or_default().to_owned(),
+                    mapped: elf
+                        .build_id()
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|x| format!("{x:02x}"))
+                        .collect(),
+                }),
+                false => Ok(()),
+            }
+        }
+        /// load bias, parsed ELF and the first mapping of the libkwin selected by `library`.
+        fn load_libkwin(
+            pid: KWinPid,
+            library: &Library,
+        ) -> Result<(usize, elf::Elf, procfs::Mapping), Error> {
             // require root permissions
-            File::open(&format!("/proc/{}/maps", pid.0))
-                .unwrap_or_else(|e| panic!("cannot open file (require permissions?)\n{:?}", e))
-                .read_to_string(&mut buffer)
-                .expect("read maps failed");
-            let buffer0 = buffer
-                .split_once("libkwin.so")
-                .expect("program does not load libkwin.so (is it really kwin_wayland?)")
-                .0;
-            let buffer1 = buffer0.rsplit_once('\n').unwrap_or(("", buffer0)).1.trim();
-            // 70642a400000-70642a54a000 r--p 00000000 103:02 3323906                   /usr/lib/libkwin.so.6.1.4
-            let Some((offset, start)) = buffer1.split_once(" r--p ") else {
-                panic!("get offset failed, the buffer line is `{buffer1}`")
+            let maps =
+                procfs::read_maps(Path::new("/proc"), pid.0).map_err(Error::MapsUnreadable)?;
+            let mappings = library.select(&maps).map_err(Error::AmbiguousLibkwin)?;
+            let first = mappings.first().ok_or(Error::LibkwinNotMapped)?;
+            // the file on disk might be replaced by an upgrade, use map_files in that case.
+            let path = first.path.as_deref().unwrap_or_default();
+            let elf = match fs::metadata(path) {
+                Ok(m) if m.ino() == first.inode && !first.deleted() => elf::Elf::open(path)?,
+                _ => elf::Elf::open(format!(
+                    "/proc/{}/map_files/{:x}-{:x}",
+                    pid.0, first.start, first.end
+                ))?,
             };
-            assert!(start.trim().starts_with("00000000"));
-            let offset1 = offset.split_once('-').expect("maps format error").0;
-            let base =
-                usize::from_str_radix(offset1, 16).expect("cannot parse to base 16") as *mut c_void;
-            let ret = unsafe { base.byte_add(workspace_offset) };
-            println!("base offset: {base:?}, {ret:?}");
-            Self(pid, ret)
+            let base = procfs::load_bias(&mappings, &elf).ok_or(Error::LibkwinNotMapped)?;
+            Ok((base, elf, (*first).clone()))
         }
-        /// using `readelf` to detect the true offset in `path_to_libkwin.so`.
+        /// read the true offset of `KWin::Workspace::_self` from the symbol table of `path_to_libkwin`, without `readelf`.
         ///
-        /// By default, param `readelf` could be str `"readelf"` since the executable `readelf` often in $PATH.
-        /// And set `path_to_libkwin` to `"/usr/lib/libkwin.so"` suits most of the cases.
-        pub fn get_offset_with_readelf(readelf: &str, path_to_libkwin: &str) -> usize {
-            usize::from_str_radix(
-                &String::from_utf8(
-                    Command::new(readelf)
-                        .args(["-WCs", path_to_libkwin])
-                        .output()
-                        .expect("readelf execute failed")
-                        .stdout,
-                )
-                .expect("failed to parse readelf")
-                .split_once(r#"KWin::Workspace::_self"#)
-                .expect("cannot find KWin::Workspace::_self")
-                .0
-                .rsplit_once('\n')
-                .expect("cannot read offset of KWin::Workspace::_self")
-                .1
-                .split_once(':')
-                .expect("parse `:` failed.")
-                .1
-                .trim()
-                .split_once(' ')
-                .expect("cannot parse space")
-                .0,
-                16,
-            )
-            .expect("failed to process readelf.")
+        /// Set `path_to_libkwin` to `"/usr/lib/libkwin.so"` suits most of the cases.
+        pub fn get_offset(path_to_libkwin: &str) -> usize {
+            Self::try_get_offset(path_to_libkwin).unwrap_or_else(|e| panic!("{e}"))
+        }
+        /// fallible version of `get_offset`.
+        pub fn try_get_offset(path_to_libkwin: &str) -> Result<usize, Error> {
+            Ok(elf::workspace_offset(path_to_libkwin)?)
+        }
+        /// kept for compatibility, `readelf` is no longer executed and the parameter is ignored.
+        #[deprecated = "use `get_offset` instead, readelf is no longer needed"]
+        pub fn get_offset_with_readelf(_readelf: &str, path_to_libkwin: &str) -> usize {
+            Self::get_offset(path_to_libkwin)
+        }
+        /// kept for compatibility, `readelf` is no longer executed and the parameter is ignored.
+        #[deprecated = "use `try_get_offset` instead, readelf is no longer needed"]
+        pub fn try_get_offset_with_readelf(
+            _readelf: &str,
+            path_to_libkwin: &str,
+        ) -> Result<usize, Error> {
+            Self::try_get_offset(path_to_libkwin)
         }
 
         /// get mouse_pos offset from pointer of workspace.
         ///
         /// Due to unsafety of KWinPid, this function is actually unsafe. Caller should ensure the pid is still valid.
         pub fn get_mouse(&self) -> Mouse {
+            self.try_get_mouse().unwrap_or_else(|e| panic!("{e}"))
+        }
+        /// fallible version of `get_mouse`.
+        pub fn try_get_mouse(&self) -> Result<Mouse, Error> {
+            self.try_get_mouse_at(self.2)
+        }
+        /// like `try_get_mouse`, with the offset of `focusMousePos` found elsewhere (e.g., `offsets::Database`).
+        pub fn try_get_mouse_at(&self, pos_offset: usize) -> Result<Mouse, Error> {
+            let addr = self.try_object()?;
+            Ok(Mouse(self.0, unsafe { addr.byte_add(pos_offset) }))
+        }
+        /// read `KWin::Workspace::_self`, which is the remote address of the `KWin::Workspace` object.
+        pub fn try_object(&self) -> Result<*mut c_void, Error> {
             let mut addr: *mut c_void = ptr::null_mut();
-            let local = iovec {
-                iov_base: &mut addr as *mut _ as *mut c_void,
-                iov_len: 8,
-            };
-            let remote = iovec {
-                iov_base: self.1,
-                iov_len: 8,
-            };
             // SAFETY: As KWinPid suggests, the safety of KWinPid ensure that the pid is valid,
             //         Since offset is ensured to be valid, the result is safe.
-            match unsafe { process_vm_readv(self.0.0, &local, 1, &remote, 1, 0) } {
-                8 => assert!(!addr.is_null()),
-                -1 => {
-                    eprintln!("failed, check errno for more details.")
-                }
-                x => eprintln!("unknown bytes readed: {x}"),
+            read_remote(self.0, &mut addr as *mut _ as *mut c_void, self.1, 8)?;
+            if addr.is_null() {
+                return Err(Error::NullWorkspace);
             }
+            Ok(addr)
+        }
+        /// build from a remote address of `KWin::Workspace::_self` computed elsewhere.
+        ///
+        /// SAFETY: `addr` should point to `KWin::Workspace::_self` in the memory of `pid`.
+        pub unsafe fn from_raw(pid: KWinPid, addr: *mut c_void) -> Self {
             // SAFETY: the offset is readed by bindgen.
-            Mouse(self.0, unsafe { addr.byte_add(POS_OFFSET) })
+            Self(pid, addr, unsafe { POS_OFFSET })
+        }
+        /// use `pos_offset` as the offset of `focusMousePos` in `get_mouse`.
+        pub fn with_pos_offset(mut self, pos_offset: usize) -> Self {
+            self.2 = pos_offset;
+            self
+        }
+        /// pid of kwin_wayland.
+        pub fn pid(&self) -> KWinPid {
+            self.0
+        }
+        /// remote address of `KWin::Workspace::_self`.
+        pub fn addr(&self) -> *mut c_void {
+            self.1
+        }
+        /// offset of `focusMousePos` used by `get_mouse`.
+        pub fn pos_offset(&self) -> usize {
+            self.2
         }
     }
     /// pointer of focusMousePos
@@ -218,27 +553,44 @@ pub mod pointer {
     impl Mouse {
         /// read mouse location from kwin workspace (it is read-only object, cannot write back.)
         pub fn loc(&self) -> (f64, f64) {
+            self.try_loc()
+                .unwrap_or_else(|e| panic!("reading failed. ({e})"))
+        }
+        /// fallible version of `loc`.
+        pub fn try_loc(&self) -> Result<(f64, f64), Error> {
             let mut xy = [0f64; 2];
-            let local = iovec {
-                iov_base: xy.as_mut_ptr() as *mut c_void,
-                iov_len: 16,
-            };
-            let remote = iovec {
-                iov_base: self.1,
-                iov_len: 16,
-            };
             // SAFETY: If you could read the code, it is safe.
             //         Otherwise it is very unsafe.
-            match unsafe { process_vm_readv(self.0.0, &local, 1, &remote, 1, 0) } {
-                16 => return (xy[0], xy[1]),
-                -1 => {
-                    eprintln!("failed, check errno for more details.")
-                }
-                x => eprintln!("unknown bytes readed: {x}"),
-            }
-            panic!("reading failed.");
+            read_remote(self.0, xy.as_mut_ptr() as *mut c_void, self.1, 16)?;
+            Ok((xy[0], xy[1]))
+        }
+        /// build from a remote address of `focusMousePos` computed elsewhere.
+        ///
+        /// SAFETY: `addr` should point to two f64 in the memory of `pid`.
+        pub unsafe fn from_raw(pid: KWinPid, addr: *mut c_void) -> Self {
+            Self(pid, addr)
+        }
+        /// pid of kwin_wayland.
+        pub fn pid(&self) -> KWinPid {
+            self.0
+        }
+        /// remote address of `focusMousePos`.
+        pub fn addr(&self) -> *mut c_void {
+            self.1
+        }
+        /// register the position into `snapshot`, so it could be read together with other fields.
+        pub fn register(&self, snapshot: &mut Snapshot) -> Field<(f64, f64)> {
+            snapshot.field(self.1)
         }
     }
+    include!("snapshot.rs");
+    include!("watch.rs");
+    include!("session.rs");
+    include!("validate.rs");
+    include!("resolve.rs");
+    #[cfg_attr(doc, doc(cfg(feature = "uinput")))]
+    #[cfg(feature = "uinput")]
+    include!("control.rs");
     /// allow print mouse location directly.
     impl Display for Mouse {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -258,10 +610,17 @@ mod test {
         let w1 = unsafe { Workspace::new(true) }; // most simple way. Note: if use suid and running the program as the wayland user, use Workspace::new(false) could be better.
 
         let pid = unsafe { KWinPid::search(true) }; // calc pid
-        let offset = Workspace::get_offset_with_readelf("readelf", "/usr/lib/libkwin.so"); // calc offset
+        let offset = Workspace::get_offset("/usr/lib/libkwin.so"); // calc offset
         let w2 = Workspace::get(pid, offset); // get workspace from pid and offset
         assert!(w1 == w2);
-        assert!(WORKSPACE_OFFSET == offset);
+        assert!(unsafe { WORKSPACE_OFFSET } == offset);
+    }
+    #[test]
+    fn session_loc() {
+        let mut session = pointer::Session::new(procfs::Scanner::new());
+        session.on_reattach(|x| println!("attached to {} (was {:?})", x.new_pid, x.old_pid));
+        println!("{:?}", session.loc().unwrap());
+        assert!(session.alive());
     }
     #[test]
     fn get_loc() {
@@ -269,4 +628,916 @@ mod test {
         let mouse = workspace.get_mouse();
         println!("{:?} {}", mouse.loc(), mouse);
     }
+    #[test]
+    fn fake_proc() {
+        use procfs::{Filter, Scanner};
+        use std::fs;
+        let root = std::env::temp_dir().join(format!("kwin-mouse-loc.proc.{}", std::process::id()));
+        let fake = |pid: i32, comm: &str, uid: u32, session: u32, maps: &str| {
+            let dir = root.join(pid.to_string());
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("comm"), format!("{comm}\n")).unwrap();
+            fs::write(
+                dir.join("cmdline"),
+                format!("/usr/bin/{comm}\0--xwayland\0"),
+            )
+            .unwrap();
+            fs::write(
+                dir.join("status"),
+                format!("Name:\t{comm}\nUid:\t{uid}\t{uid}\t{uid}\t{uid}\n"),
+            )
+            .unwrap();
+            fs::write(
+                dir.join("stat"),
+                format!(
+                    "{pid} ({comm}) S 1 {pid} {pid} 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 {} 0 0",
+                    pid * 10
+                ),
+            )
+            .unwrap();
+            fs::write(dir.join("sessionid"), session.to_string()).unwrap();
+            fs::write(dir.join("maps"), maps).unwrap();
+        };
+        fake(
+            100,
+            "kwin_wayland",
+            1000,
+            2,
+            "7064000-7065000 r--p 00000000 103:02 1 /usr/lib/libkwin.so.6\n",
+        );
+        fake(200, "kwin_wayland", 0, 3, "");
+        fake(
+            300,
+            "plasmashell",
+            1000,
+            2,
+            "7064000-7065000 r--p 00000000 103:02 1 /usr/lib/libkwin.so.6\n",
+        );
+        fs::create_dir_all(root.join("self")).unwrap();
+        fs::write(root.join("self/sessionid"), "2").unwrap();
+
+        let all = Scanner::with_root(&root).scan().unwrap();
+        assert_eq!(all.iter().map(|c| c.pid).collect::<Vec<_>>(), [100, 200]);
+        assert_eq!(all[0].start_time, 1000);
+        assert_eq!(all[0].cmdline, ["/usr/bin/kwin_wayland", "--xwayland"]);
+        let pids = |s: Scanner| s.scan().unwrap().iter().map(|c| c.pid).collect::<Vec<_>>();
+        assert_eq!(
+            pids(Scanner::with_root(&root).filter(Filter::Uid(0))),
+            [200]
+        );
+        assert_eq!(
+            pids(Scanner::with_root(&root).filter(Filter::SameSession)),
+            [100]
+        );
+        assert_eq!(
+            pids(Scanner::with_root(&root).filter(Filter::LibkwinMapped)),
+            [100]
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+    #[test]
+    fn fake_privilege() {
+        use privilege::{Access, Report};
+        use std::fs;
+        let root = std::env::temp_dir().join(format!("kwin-mouse-loc.priv.{}", std::process::id()));
+        let status = |uid: u32, cap: &str| {
+            format!("Uid:\t{uid}\t{uid}\t{uid}\t{uid}\nCapPrm:\t{cap}\nCapEff:\t{cap}\n")
+        };
+        fs::create_dir_all(root.join("self")).unwrap();
+        fs::create_dir_all(root.join("100")).unwrap();
+        fs::create_dir_all(root.join("sys/kernel/yama")).unwrap();
+        fs::write(root.join("100/status"), status(1000, "0000000000000000")).unwrap();
+        // the owner of the fake `/proc/100` is whoever runs the test, thus dumpable is set explicitly.
+        let check = |uid, cap, scope: &str, dumpable| {
+            fs::write(root.join("self/status"), status(uid, cap)).unwrap();
+            fs::write(root.join("sys/kernel/yama/ptrace_scope"), scope).unwrap();
+            let mut report = Report::check_with_root(&root, 100);
+            assert!(report.dumpable.is_some());
+            report.dumpable = Some(dumpable);
+            report.access()
+        };
+        assert_eq!(check(1000, "0000000000000000", "0", true), Access::Granted);
+        // kwin_wayland is non-dumpable, the same uid is not enough.
+        assert_eq!(check(1000, "0000000000000000", "0", false), Access::Denied);
+        assert_eq!(check(1000, "0000000000080000", "0", false), Access::Granted);
+        assert_eq!(
+            check(0, "0000000000000000", "0", false),
+            Access::NeedsEscalation
+        );
+        assert_eq!(check(1000, "0000000000000000", "1", true), Access::Denied);
+        assert_eq!(check(1001, "0000000000080000", "2", false), Access::Granted);
+        assert_eq!(check(0, "0000000000080000", "3", false), Access::Denied);
+        fs::remove_dir_all(&root).unwrap();
+    }
+    #[test]
+    fn elf_lookup() {
+        let exe = elf::Elf::open("/proc/self/exe").unwrap();
+        assert!(exe.section(".text").is_some());
+        assert!(exe.lookup("main").is_ok_and(|x| x.value != 0));
+        assert!(matches!(
+            exe.lookup(elf::WORKSPACE_SELF),
+            Err(elf::Error::SymbolNotFound(_))
+        ));
+    }
+    #[allow(non_snake_case)]
+    mod KWin {
+        #[repr(C)]
+        pub struct Workspace {
+            pub vtable: usize,
+            pub flags: u32,
+            pub focusMousePos: (f64, f64),
+        }
+    }
+    #[test]
+    fn dwarf_member() {
+        let workspace = KWin::Workspace {
+            vtable: 0,
+            flags: 0,
+            focusMousePos: (0.0, 0.0),
+        };
+        std::hint::black_box(&workspace);
+        let exe = dwarf::find_debug_file("/proc/self/exe").unwrap();
+        let offset = dwarf::member_offset(
+            &elf::Elf::open(exe).unwrap(),
+            &["KWin", "Workspace"],
+            "focusMousePos",
+        );
+        assert_eq!(
+            offset.unwrap(),
+            std::mem::offset_of!(KWin::Workspace, focusMousePos) as u64
+        );
+    }
+    #[test]
+    fn dwarf_compressed_and_alt() {
+        let workspace = KWin::Workspace {
+            vtable: 0,
+            flags: 0,
+            focusMousePos: (0.0, 0.0),
+        };
+        std::hint::black_box(&workspace);
+        let expected = std::mem::offset_of!(KWin::Workspace, focusMousePos) as u64;
+        let scope = ["KWin", "Workspace"];
+        let path =
+            std::fs::canonicalize(dwarf::find_debug_file("/proc/self/exe").unwrap()).unwrap();
+        let exe = elf::Elf::open(&path).unwrap();
+        // stored zlib blocks and raw zstd blocks, which any decompressor accepts.
+        let zlib = |x: &[u8]| {
+            let mut ret = vec![0x78, 0x01];
+            let chunks = x.chunks(0xffff).collect::<Vec<_>>();
+            for (i, c) in chunks.iter().enumerate() {
+                ret.push((i + 1 == chunks.len()) as u8);
+                ret.extend((c.len() as u16).to_le_bytes());
+                ret.extend((!(c.len() as u16)).to_le_bytes());
+                ret.extend(*c);
+            }
+            let (a, b) = x.iter().fold((1u32, 0u32), |(a, b), &v| {
+                let a = (a + v as u32) % 65521;
+                (a, (b + a) % 65521)
+            });
+            ret.extend((b << 16 | a).to_be_bytes());
+            ret
+        };
+        let zstd = |x: &[u8]| {
+            let mut ret = vec![0x28, 0xb5, 0x2f, 0xfd, 0xa0];
+            ret.extend((x.len() as u32).to_le_bytes());
+            let chunks = x.chunks(1 << 17).collect::<Vec<_>>();
+            for (i, c) in chunks.iter().enumerate() {
+                let header = (c.len() as u32) << 3 | (i + 1 == chunks.len()) as u32;
+                ret.extend(&header.to_le_bytes()[..3]);
+                ret.extend(*c);
+            }
+            ret
+        };
+        // compress every debug section of the test binary, appending them with `Elf64_Chdr`.
+        let mut data = exe.data().to_vec();
+        let shoff = exe.uint::<8>(0x28).unwrap() as usize;
+        for (i, section) in exe.sections().iter().enumerate() {
+            if !section.name.starts_with(".debug_") || section.kind == elf::SHT_NOBITS {
+                continue;
+            }
+            let raw = exe.section_data(section).unwrap();
+            let (kind, payload) = match i % 2 {
+                0 => (elf::ELFCOMPRESS_ZLIB, zlib(raw)),
+                _ => (elf::ELFCOMPRESS_ZSTD, zstd(raw)),
+            };
+            let offset = data.len().next_multiple_of(8);
+            data.resize(offset, 0);
+            data.extend(kind.to_le_bytes());
+            data.extend(0u32.to_le_bytes());
+            data.extend((raw.len() as u64).to_le_bytes());
+            data.extend(1u64.to_le_bytes());
+            data.extend(payload);
+            let size = (data.len() - offset) as u64;
+            let h = shoff + i * 64;
+            data[h + 8..h + 16]
+                .copy_from_slice(&(section.flags | elf::SHF_COMPRESSED).to_le_bytes());
+            data[h + 24..h + 32].copy_from_slice(&(offset as u64).to_le_bytes());
+            data[h + 32..h + 40].copy_from_slice(&size.to_le_bytes());
+        }
+        let compressed = elf::Elf::parse(data).unwrap();
+        let str = compressed.section(".debug_str").unwrap();
+        assert!(str.flags & elf::SHF_COMPRESSED != 0);
+        assert_eq!(
+            *compressed.section_contents(str).unwrap(),
+            *exe.section_data(exe.section(".debug_str").unwrap())
+                .unwrap()
+        );
+        assert_eq!(
+            dwarf::member_offset(&compressed, &scope, "focusMousePos").unwrap(),
+            expected
+        );
+
+        // a minimal ELF64 with the given sections, as dwz leaves the main file.
+        let tiny = |sections: &[(&str, Vec<u8>)]| {
+            let mut sections = sections.to_vec();
+            let mut names = vec![0u8];
+            let offsets = sections
+                .iter()
+                .map(|x| x.0)
+                .chain([".shstrtab"])
+                .map(|x| {
+                    let off = names.len() as u64;
+                    names.extend(x.as_bytes());
+                    names.push(0);
+                    off
+                })
+                .collect::<Vec<_>>();
+            sections.push((".shstrtab", names));
+            let mut data = vec![0u8; 64];
+            data[..7].copy_from_slice(b"\x7fELF\x02\x01\x01");
+            // sh_name | SHT_PROGBITS, flags, addr, offset, size, link, addralign, entsize
+            let mut headers = vec![[0u64; 8]];
+            for ((_, content), name) in sections.iter().zip(offsets) {
+                headers.push([
+                    name | 1 << 32,
+                    0,
+                    0,
+                    data.len() as u64,
+                    content.len() as u64,
+                    0,
+                    1,
+                    0,
+                ]);
+                data.extend(content);
+            }
+            let shoff = data.len() as u64;
+            for h in &headers {
+                h.iter().for_each(|x| data.extend(x.to_le_bytes()));
+            }
+            data[0x28..0x30].copy_from_slice(&shoff.to_le_bytes());
+            data[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+            data[0x3c..0x3e].copy_from_slice(&(headers.len() as u16).to_le_bytes());
+            data[0x3e..0x40].copy_from_slice(&(headers.len() as u16 - 1).to_le_bytes());
+            elf::Elf::parse(data).unwrap()
+        };
+        let unit = |entries: &[u8]| {
+            let mut ret = (entries.len() as u32 + 7).to_le_bytes().to_vec();
+            ret.extend([4, 0, 0, 0, 0, 0, 8]);
+            ret.extend(entries);
+            ret
+        };
+        let mut link = path.to_str().unwrap().as_bytes().to_vec();
+        link.push(0);
+        link.extend(exe.build_id().unwrap());
+        // every entry was moved into the supplementary file.
+        let main = tiny(&[
+            (".debug_info", unit(&[0])),
+            (".debug_abbrev", vec![0]),
+            (".gnu_debugaltlink", link.clone()),
+        ]);
+        assert_eq!(
+            dwarf::member_offset(&main, &scope, "focusMousePos").unwrap(),
+            expected
+        );
+        // names in `.debug_str` of the supplementary file, by `DW_FORM_GNU_strp_alt`.
+        let strs = exe
+            .section_data(exe.section(".debug_str").unwrap())
+            .unwrap();
+        let alt_str = |x: &str| {
+            let x = [b"\0", x.as_bytes(), b"\0"].concat();
+            let off = strs.windows(x.len()).position(|w| w == x).unwrap() as u32 + 1;
+            off.to_le_bytes()
+        };
+        let mut entries = [vec![1], alt_str("KWin").to_vec(), vec![2]].concat();
+        entries.extend(alt_str("Workspace"));
+        entries.push(3);
+        entries.extend(alt_str("focusMousePos"));
+        entries.extend([0x34, 0x12, 0, 0]);
+        let abbrev = vec![
+            1, 0x39, 1, 0x03, 0xa1, 0x3e, 0, 0, // namespace
+            2, 0x02, 1, 0x03, 0xa1, 0x3e, 0, 0, // class
+            3, 0x0d, 0, 0x03, 0xa1, 0x3e, 0x38, 0x05, 0, 0, // member, data2 location
+            0,
+        ];
+        let main = tiny(&[
+            (".debug_info", unit(&entries)),
+            (".debug_abbrev", abbrev),
+            (".gnu_debugaltlink", link),
+        ]);
+        assert_eq!(
+            dwarf::member_offset(&main, &scope, "focusMousePos").unwrap(),
+            0x1234
+        );
+        // the supplementary file is missing.
+        let main = tiny(&[
+            (".debug_info", unit(&[0])),
+            (".debug_abbrev", vec![0]),
+            (
+                ".gnu_debugaltlink",
+                b"/nonexistent/dwz\0\x01\x02\x03\x04".to_vec(),
+            ),
+        ]);
+        assert!(matches!(
+            dwarf::member_offset(&main, &scope, "focusMousePos"),
+            Err(dwarf::Error::NoAltFile(x)) if x == "/nonexistent/dwz"
+        ));
+    }
+    #[test]
+    fn maps_and_load_bias() {
+        use procfs::{Library, Mapping, parse_maps};
+        let maps = parse_maps(
+            "70642a400000-70642a54a000 r-xp 00001000 103:02 3323906                   /usr/lib/libkwin.so.6.1.4
+70642a54a000-70642a600000 r--p 00000000 103:02 3323906                   /usr/lib/libkwin.so.6.1.4
+70642b000000-70642b001000 r--p 00000000 103:02 42 /usr/lib/qt6/plugins/libkwin.so.6/libkwin.so.plugin.so
+70642c000000-70642c021000 rw-p 00000000 00:00 0                          [heap]
+70642d000000-70642d001000 rw-p 00000000 00:00 0",
+        );
+        assert_eq!(maps.len(), 5);
+        assert_eq!(maps[3].path.as_deref(), Some("[heap]"));
+        assert_eq!(maps[4].path, None);
+        assert!(maps[0].executable() && !maps[1].executable());
+        let selected = Library::default().select(&maps).unwrap();
+        assert_eq!(selected.len(), 2);
+        assert_eq!(Library::Inode(42).select(&maps).unwrap(), [&maps[2]]);
+        assert!(Mapping::parse("garbage").is_none());
+
+        #[unsafe(no_mangle)]
+        static KWIN_MOUSE_LOC_LOAD_BIAS_TEST: u8 = 0;
+        let exe = std::env::current_exe().unwrap();
+        let maps =
+            procfs::read_maps(std::path::Path::new("/proc"), std::process::id() as i32).unwrap();
+        let selected = Library::Path(exe.clone()).select(&maps).unwrap();
+        let elf = elf::Elf::open(&exe).unwrap();
+        let bias = procfs::load_bias(&selected, &elf).unwrap();
+        let sym = elf.lookup("KWIN_MOUSE_LOC_LOAD_BIAS_TEST").unwrap();
+        assert_eq!(
+            bias + sym.value as usize,
+            &KWIN_MOUSE_LOC_LOAD_BIAS_TEST as *const u8 as usize
+        );
+    }
+    #[test]
+    fn offset_database() {
+        use offsets::{Database, Error, Key};
+        let db = Database::parse(
+            "# comment
+[build-id.C0FFEE]
+workspace = 0x6a5f80 # trailing comment
+pos = 176
+
+[version.\"6.0.5\"]
+workspace = 6946720
+activeWindow = 0x98
+",
+        )
+        .unwrap();
+        let entry = db.lookup(Some(&[0xc0, 0xff, 0xee]), Some("6.0.5")).unwrap();
+        assert_eq!((entry.workspace, entry.pos), (Some(0x6a5f80), Some(176)));
+        let entry = db.lookup(Some(&[1]), Some("6.0.5")).unwrap();
+        assert_eq!(entry.workspace, Some(0x69ffa0));
+        assert_eq!(entry.fields["activeWindow"], 0x98);
+        assert!(db.lookup(None, Some("6.1")).is_none());
+        assert_eq!(Database::parse(&db.to_string()).unwrap(), db);
+        let dir = std::env::temp_dir().join(format!("kwin-mouse-loc.cache.{}", std::process::id()));
+        let path = dir.join("nested/offsets");
+        assert_eq!(Database::load_or_empty(&path).unwrap(), Database::new());
+        db.save(&path).unwrap();
+        db.save(&path).unwrap();
+        assert_eq!(Database::load(&path).unwrap(), db);
+        assert_eq!(
+            std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+            1
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(db.get(&Key::Version("6.0.5".into())).is_some());
+        assert!(matches!(
+            Database::parse("pos = 1"),
+            Err(Error::Parse(1, _))
+        ));
+        assert!(matches!(
+            Database::parse("[version.1]\npos = x"),
+            Err(Error::Parse(2, _))
+        ));
+    }
+    #[cfg(feature = "update-offset")]
+    #[test]
+    fn update_dry_run() {
+        use consts::{Storage, UpdateError, UpdateOptions, try_update_offset};
+        let options = UpdateOptions::new().kwin(None).dry_run(true);
+        let report = try_update_offset(&options).unwrap();
+        assert!(!report.written && !report.changed());
+        assert_eq!(report.workspace(), unsafe { WORKSPACE_OFFSET });
+        let exe = std::env::current_exe().unwrap();
+        let cache =
+            std::env::temp_dir().join(format!("kwin-mouse-loc.update.{}", std::process::id()));
+        let options = UpdateOptions::new()
+            .kwin(exe.to_str())
+            .storage(Storage::Cache(cache.clone()));
+        assert!(matches!(
+            try_update_offset(&options),
+            Err(UpdateError::Elf(elf::Error::SymbolNotFound(_)))
+        ));
+        assert!(matches!(
+            consts::try_save_offset_to_cache(
+                &cache,
+                exe.to_str().unwrap(),
+                0,
+                consts::Offset::Offset
+            ),
+            Err(UpdateError::NotCacheable)
+        ));
+        assert!(!cache.exists());
+    }
+    #[cfg(feature = "update-offset")]
+    #[test]
+    fn patch_sections() {
+        use consts::{Offset, Patched, SaveError, patch_offset};
+        let dir = std::env::temp_dir().join(format!("kwin-mouse-loc.patch.{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let copy = dir.join("exe");
+        std::fs::copy(std::env::current_exe().unwrap(), &copy).unwrap();
+        let read = |name| {
+            let elf = elf::Elf::open(&copy).unwrap();
+            let data = elf.section_data(elf.section(name).unwrap()).unwrap();
+            usize::from_ne_bytes(data[..8].try_into().unwrap())
+        };
+        assert_eq!(
+            patch_offset(&copy, 0x1234, Offset::Pos).unwrap(),
+            Patched::InPlace
+        );
+        assert_eq!(read(Offset::Pos.section()), 0x1234);
+        // the sentinels are kept, thus patching again is fine.
+        patch_offset(&copy, 0x5678, Offset::Kwin).unwrap();
+        assert_eq!(read(Offset::Kwin.section()), 0x5678);
+        assert_eq!(read(Offset::Pos.section()), 0x1234);
+        let elf = elf::Elf::open(&copy).unwrap();
+        let offset = elf.section(Offset::Offset.section()).unwrap().offset as usize;
+        let mut data = std::fs::read(&copy).unwrap();
+        data[offset + 8] ^= 0xff;
+        std::fs::write(&copy, data).unwrap();
+        assert!(matches!(
+            patch_offset(&copy, 0, Offset::Pos),
+            Err(SaveError::SentinelMismatch(_))
+        ));
+        assert_eq!(read(Offset::Pos.section()), 0x1234);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+    #[cfg(feature = "update-offset")]
+    #[test]
+    fn offset_history() {
+        use consts::{
+            HistoryEntry, Storage, UpdateError, list_offset_history_at, record_offset_history,
+            rollback_offset_with,
+        };
+        use offsets::{Database, Key};
+        let dir =
+            std::env::temp_dir().join(format!("kwin-mouse-loc.history.{}", std::process::id()));
+        let (history, cache) = (dir.join("history"), dir.join("offsets"));
+        assert_eq!(list_offset_history_at(&history).unwrap(), []);
+        // the statics are only read, thus other tests reading them in parallel are not affected.
+        let (workspace, pos) = unsafe { (WORKSPACE_OFFSET, consts::POS_OFFSET) };
+        let entry = HistoryEntry {
+            timestamp: 1729238400,
+            kwin: "/usr/lib/libkwin.so".into(),
+            build_id: Some("3c0ffee0ddba11".into()),
+            storage: Storage::Cache(cache.clone()),
+            old_workspace: Some(workspace),
+            old_pos: Some(pos),
+            new_workspace: Some(0x6a6f80),
+            new_pos: Some(0xb8),
+        };
+        record_offset_history(&history, &entry).unwrap();
+        assert_eq!(
+            list_offset_history_at(&history).unwrap(),
+            std::slice::from_ref(&entry)
+        );
+        assert!(matches!(
+            rollback_offset_with(&history, 1, (workspace, pos), |_| unreachable!()),
+            Err(UpdateError::NoHistory(1))
+        ));
+        let mut applied = None;
+        let report =
+            rollback_offset_with(&history, 0, (workspace, pos), |x| applied = Some(x.clone()))
+                .unwrap();
+        assert_eq!(applied.as_ref(), Some(&report));
+        assert_eq!((report.workspace(), report.pos()), (workspace, pos));
+        let db = Database::load(&cache).unwrap();
+        let restored = db.get(&Key::BuildId("3c0ffee0ddba11".into())).unwrap();
+        assert_eq!(
+            (restored.workspace, restored.pos),
+            (Some(workspace), Some(pos))
+        );
+        let list = list_offset_history_at(&history).unwrap();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[1].build_id, entry.build_id);
+        assert_eq!(list[1].new_workspace, Some(workspace));
+        std::fs::write(&history, "1\tbroken\n").unwrap();
+        assert!(matches!(
+            list_offset_history_at(&history),
+            Err(UpdateError::History(offsets::Error::Parse(1, _)))
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+    #[cfg(feature = "update-offset")]
+    #[test]
+    fn cache_rollback() {
+        use consts::{
+            OffsetUpdate, Storage, list_offset_history_at, record_offset_history,
+            rollback_offset_with, save_update,
+        };
+        use offsets::{Database, Entry, Key};
+        let dir = std::env::temp_dir().join(format!(
+            "kwin-mouse-loc.cache-rollback.{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        // keyed by its build-id, or the version suffix of the name without one.
+        let kwin = dir.join("libkwin.so.6.9.9");
+        std::fs::copy(std::env::current_exe().unwrap(), &kwin).unwrap();
+        let kwin = kwin.to_str().unwrap();
+        let key = Key::of_library(kwin).unwrap();
+        let (history, cache) = (dir.join("history"), dir.join("offsets"));
+        let storage = Storage::Cache(cache.clone());
+        // the statics are only read, the update is saved by `save_update` alone.
+        let current = unsafe { (WORKSPACE_OFFSET, consts::POS_OFFSET) };
+        let report = OffsetUpdate {
+            old_workspace: current.0,
+            old_pos: current.1,
+            new_workspace: Some(0x6a6f80),
+            new_pos: Some(0xb8),
+            pos_skipped: None,
+            validation: None,
+            written: true,
+        };
+        let offsets = |db: &Database| db.get(&key).map(|x| (x.workspace, x.pos));
+        let before = Entry {
+            workspace: Some(0x100),
+            pos: Some(0x10),
+            fields: [("activeWindow".to_owned(), 0x98)].into(),
+        };
+        for prior in [Some(before), None] {
+            let mut db = Database::new();
+            if let Some(x) = &prior {
+                db.insert(key.clone(), x.clone());
+            }
+            db.save(&cache).unwrap();
+            let entry = save_update(&storage, kwin, &report).unwrap();
+            assert_eq!(
+                (entry.old_workspace, entry.old_pos),
+                offsets(&db).unwrap_or_default()
+            );
+            record_offset_history(&history, &entry).unwrap();
+            assert_eq!(
+                offsets(&Database::load(&cache).unwrap()),
+                Some((Some(0x6a6f80), Some(0xb8)))
+            );
+            rollback_offset_with(&history, 0, current, |_| {}).unwrap();
+            assert_eq!(Database::load(&cache).unwrap().get(&key), prior.as_ref());
+            // rolling back the rollback redoes the update.
+            rollback_offset_with(&history, 0, current, |_| {}).unwrap();
+            assert_eq!(
+                offsets(&Database::load(&cache).unwrap()),
+                Some((Some(0x6a6f80), Some(0xb8)))
+            );
+            let list = list_offset_history_at(&history).unwrap();
+            assert_eq!(list[list.len() - 2].new_workspace, entry.old_workspace);
+            std::fs::remove_file(&history).unwrap();
+        }
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+    #[cfg(feature = "update-offset")]
+    #[test]
+    fn package_hooks() {
+        use consts::{Hook, PackageManager, run_subcommand};
+        let root =
+            std::env::temp_dir().join(format!("kwin-mouse-loc.hooks.{}", std::process::id()));
+        std::fs::create_dir_all(root.join("etc/dnf/libdnf5-plugins")).unwrap();
+        std::fs::write(root.join("etc/pacman.conf"), "").unwrap();
+        std::fs::write(root.join("etc/dnf/libdnf5-plugins/actions.conf"), "").unwrap();
+        assert_eq!(
+            PackageManager::detect(&root),
+            [PackageManager::Pacman, PackageManager::Dnf5]
+        );
+        assert_eq!(consts::find_libkwin(&root), None);
+        let triplet = format!("usr/lib/{}-linux-gnu", std::env::consts::ARCH);
+        for dir in ["usr/lib", "usr/lib64", &triplet] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join("libkwin.so.6"), "").unwrap();
+            assert_eq!(
+                consts::find_libkwin(&root),
+                Some(format!("/{dir}/libkwin.so.6").into())
+            );
+        }
+        let exe = std::env::current_exe().unwrap();
+        let args = |x: &[&str]| {
+            [x, &["--root", root.to_str().unwrap()]]
+                .concat()
+                .into_iter()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(run_subcommand(&args(&["install-hooks"])), Some(0));
+        assert_eq!(
+            run_subcommand(&args(&["install-hooks", "dpkg", "rpm"])),
+            Some(0)
+        );
+        for manager in PackageManager::ALL {
+            let hook = Hook::new(manager, &exe);
+            let content = std::fs::read_to_string(root.join(hook.path())).unwrap();
+            assert_eq!(content, hook.render().unwrap());
+            assert!(content.contains(&format!("{} update-offset", exe.display())));
+            assert!(content.contains(manager.libkwin_files()));
+        }
+        let hook = Hook::new(PackageManager::Dnf5, &exe).kwin("/usr/lib64/libkwin.so.6");
+        assert_eq!(
+            hook.render().unwrap(),
+            format!(
+                "post_transaction:/usr/lib64/libkwin.so.*:in::{} update-offset --kwin /usr/lib64/libkwin.so.6\n",
+                exe.display()
+            )
+        );
+        assert_eq!(
+            run_subcommand(&args(&[
+                "install-hooks",
+                "--kwin",
+                "/usr/lib64/libkwin.so.6",
+                "dnf5"
+            ])),
+            Some(0)
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join(hook.path())).unwrap(),
+            hook.render().unwrap()
+        );
+        assert_eq!(run_subcommand(&args(&["update-offset", "--pos"])), Some(2));
+        assert_eq!(run_subcommand(&args(&["install-hooks", "yum"])), Some(2));
+        assert_eq!(run_subcommand(&args(&["not-a-command"])), None);
+        assert!(
+            Hook::new(PackageManager::Rpm, "/opt/my clicker")
+                .render()
+                .is_err()
+        );
+        assert_eq!(
+            run_subcommand(&args(&["uninstall-hooks", "pacman", "dpkg", "rpm", "dnf5"])),
+            Some(0)
+        );
+        for manager in PackageManager::ALL {
+            assert!(!root.join(Hook::new(manager, &exe).path()).exists());
+        }
+        std::fs::remove_dir_all(root).unwrap();
+    }
+    #[test]
+    fn build_info() {
+        use consts::{BUILD_INFO, BuildInfo};
+        let info = BuildInfo {
+            build_id: Some("0a1bff"),
+            ..BUILD_INFO
+        };
+        assert_eq!(info.matches(Some(&[0x0a, 0x1b, 0xff])), Some(true));
+        assert_eq!(info.matches(Some(&[0x0a, 0x1b])), Some(false));
+        assert_eq!(info.matches(Some(&[0x0a, 0x1b, 0xfe])), Some(false));
+        assert_eq!(info.matches(None), None);
+        assert!(info.to_string().contains("build-id 0a1bff"));
+    }
+    #[cfg(feature = "uinput")]
+    #[test]
+    fn abs_pointer_scale() {
+        use device::{ABS_RANGE, to_abs};
+        use pointer::Rect;
+        let outputs = [
+            Rect::new(-1280.0, 56.0, 1280.0, 1024.0),
+            Rect::new(0.0, 0.0, 1920.0, 1080.0),
+        ];
+        let bounds = Rect::bounding(&outputs).unwrap();
+        assert_eq!(bounds, Rect::new(-1280.0, 0.0, 3200.0, 1080.0));
+        assert_eq!(Rect::bounding(&[]), None);
+        assert_eq!(to_abs(&bounds, -1280.0, 0.0), (0, 0));
+        assert_eq!(to_abs(&bounds, 320.0, 540.0), (0x8000, 0x8000));
+        // libinput maps `v` back to `v * size / (ABS_RANGE + 1)`.
+        let (x, y) = to_abs(&bounds, 1000.0, 777.0);
+        assert!((x as f64 * 3200.0 / 65536.0 - 2280.0).abs() < 0.05);
+        assert!((y as f64 * 1080.0 / 65536.0 - 777.0).abs() < 0.05);
+        assert_eq!(to_abs(&bounds, 1920.0, 1080.0), (ABS_RANGE, ABS_RANGE));
+        assert_eq!(to_abs(&bounds, -5000.0, 5000.0), (0, ABS_RANGE));
+        assert_eq!(to_abs(&Rect::new(0.0, 0.0, 0.0, 0.0), 10.0, 10.0), (0, 0));
+    }
+    #[cfg(feature = "uinput")]
+    #[test]
+    fn wheel_detents() {
+        use device::{WHEEL_DETENT, whole_detents};
+        let mut remainder = 0;
+        assert_eq!(whole_detents(&mut remainder, 60), 0);
+        assert_eq!(whole_detents(&mut remainder, 60), 1);
+        assert_eq!(remainder, 0);
+        assert_eq!(whole_detents(&mut remainder, 300), 2);
+        assert_eq!(remainder, 60);
+        // the remainder keeps its sign, reversing first cancels it.
+        assert_eq!(whole_detents(&mut remainder, -100), 0);
+        assert_eq!(remainder, -40);
+        assert_eq!(whole_detents(&mut remainder, -200), -2);
+        assert_eq!(remainder, 0);
+        remainder = WHEEL_DETENT - 1;
+        assert_eq!(
+            whole_detents(&mut remainder, i32::MAX),
+            i32::MAX / WHEEL_DETENT
+        );
+        assert_eq!(remainder, i32::MAX % WHEEL_DETENT);
+    }
+    #[cfg(feature = "uinput")]
+    #[test]
+    fn keyboard_layout() {
+        use device::{KEY_1, KEY_E, KEY_H, KEY_SPACE, Layout, Modifiers};
+        let layout = Layout::us();
+        assert_eq!(layout.get('h'), Some((KEY_H, Modifiers::NONE)));
+        assert_eq!(layout.get('H'), Some((KEY_H, Modifiers::SHIFT)));
+        assert_eq!(layout.get('!'), Some((KEY_1, Modifiers::SHIFT)));
+        assert_eq!(layout.get(' '), Some((KEY_SPACE, Modifiers::NONE)));
+        assert_eq!(layout.unsupported("Hello World!"), []);
+        assert_eq!(
+            layout.unsupported("Grüße €"),
+            [(2, 'ü'), (4, 'ß'), (8, '€')]
+        );
+        let layout = layout.with('€', KEY_E, Modifiers::ALTGR);
+        assert_eq!(layout.unsupported("€"), []);
+    }
+    #[cfg(feature = "uinput")]
+    #[test]
+    fn controller_gain() {
+        use pointer::{bucket, gain, learn};
+        assert_eq!(bucket(0.5), 0);
+        assert_eq!(bucket(3.0), 1);
+        assert_eq!(bucket(64.0), 6);
+        assert_eq!(bucket(1e9), 15);
+        let mut gains = [None; 16];
+        assert_eq!(gain(&gains, 100.0), 1.0);
+        gains[2] = Some(0.5);
+        gains[6] = Some(2.0);
+        assert_eq!(gain(&gains, 1.0), 0.5);
+        assert_eq!(gain(&gains, 100.0), 2.0);
+        // equally far from both, the smaller movement wins.
+        assert_eq!(gain(&gains, 16.0), 0.5);
+        assert_eq!(learn((30.0, 40.0), (60.0, 80.0)), Some(2.0));
+        assert_eq!(learn((10.0, 0.0), (-5.0, 0.0)), None);
+        assert_eq!(learn((0.0, -8.0), (0.0, -12.0)), Some(1.5));
+        // clamped at the edges, entirely or on one axis.
+        assert_eq!(learn((30.0, 40.0), (0.0, 0.0)), None);
+        assert_eq!(learn((30.0, 40.0), (60.0, 0.0)), None);
+        assert_eq!(learn((30.0, 40.0), (60.0, 20.0)), None);
+        assert_eq!(learn((30.0, 0.0), (60.0, 15.0)), None);
+    }
+    #[cfg(feature = "uinput")]
+    #[test]
+    fn calibrate_score() {
+        use consts::score;
+        let moves = [(40, 0), (0, 40), (-40, -40)];
+        // offset 0 follows every move, 8 is a constant, 16 is unreadable once, 24 moves the wrong way.
+        let readings = [(0.0, 0.0), (25.0, 0.0), (25.0, 30.0), (0.0, 0.0)]
+            .iter()
+            .enumerate()
+            .map(|(k, &p)| {
+                vec![
+                    Some(p),
+                    Some((7.0, 7.0)),
+                    (k != 2).then_some(p),
+                    Some((-p.0, p.1)),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let calibration = score(&moves, &readings);
+        assert_eq!(calibration.offset, 0);
+        assert_eq!(calibration.ties, [0]);
+        assert_eq!(calibration.confidence, 1.0);
+        // a twin of the position halves the confidence.
+        let twins = readings
+            .iter()
+            .map(|x| [x[0], x[0]].to_vec())
+            .collect::<Vec<_>>();
+        let calibration = score(&moves, &twins);
+        assert_eq!(calibration.ties, [0, 8]);
+        assert_eq!(calibration.confidence, 0.5);
+        // nothing tracks the cursor.
+        assert!(score(&moves, &[vec![None], vec![None]]).ties.is_empty());
+        assert_eq!(score(&moves, &[]).confidence, 0.0);
+    }
+    #[test]
+    fn snapshot_partial() {
+        use pointer::{Error, Snapshot};
+        use std::ffi::c_void;
+        let xy = [1.5f64, -2.0];
+        let n = 42usize;
+        let mut snapshot = Snapshot::new(unsafe { KWinPid::from(std::process::id() as i32) });
+        let pos = snapshot.field::<(f64, f64)>(xy.as_ptr() as *mut c_void);
+        let num = snapshot.field::<usize>(&n as *const usize as *mut c_void);
+        let bad = snapshot.field::<u64>(std::ptr::null_mut());
+        let after = snapshot.field::<usize>(&n as *const usize as *mut c_void);
+        let reading = snapshot.read().unwrap();
+        assert!(!reading.complete());
+        assert_eq!(reading.get(pos).unwrap(), (1.5, -2.0));
+        assert_eq!(reading.get(num).unwrap(), 42);
+        assert!(matches!(
+            reading.get(bad),
+            Err(Error::ShortRead {
+                expected: 8,
+                read: 0
+            })
+        ));
+        assert!(reading.get(after).is_err());
+    }
+    #[test]
+    fn watch_changes() {
+        use pointer::{Mouse, WatchOptions};
+        use std::{
+            sync::atomic::{AtomicU64, Ordering},
+            time::Duration,
+        };
+        static XY: [AtomicU64; 2] = [AtomicU64::new(0), AtomicU64::new(0)];
+        let pid = unsafe { KWinPid::from(std::process::id() as i32) };
+        let mouse = unsafe { Mouse::from_raw(pid, XY.as_ptr() as *mut std::ffi::c_void) };
+        let mover = std::thread::spawn(|| {
+            for x in [1.0f64, 1.5, 10.0] {
+                std::thread::sleep(Duration::from_millis(30));
+                XY[0].store(x.to_bits(), Ordering::Relaxed);
+            }
+        });
+        let options = WatchOptions::new(Duration::from_millis(5))
+            .epsilon(1.0)
+            .idle_timeout(Duration::from_millis(150));
+        let samples = mouse
+            .watch_with(options)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        mover.join().unwrap();
+        // 0 -> 1 is not farther than epsilon, thus merged into the next sample.
+        assert_eq!(
+            samples.iter().map(|x| x.pos).collect::<Vec<_>>(),
+            [(1.5, 0.0), (10.0, 0.0)]
+        );
+        assert_eq!(samples[1].delta, (8.5, 0.0));
+        assert!(samples[1].velocity.0 > 0.0);
+        // a move within epsilon is never reported, but the watch still becomes idle afterwards.
+        let mover = std::thread::spawn(|| {
+            std::thread::sleep(Duration::from_millis(30));
+            XY[0].store(10.5f64.to_bits(), Ordering::Relaxed);
+        });
+        assert_eq!(mouse.watch_with(options).count(), 0);
+        mover.join().unwrap();
+        assert_eq!(options.max_rate(0.0), options);
+        assert_eq!(options.max_rate(f64::NAN), options);
+    }
+    #[cfg(feature = "async")]
+    #[test]
+    fn stream_changes() {
+        use pointer::Mouse;
+        use std::{os::fd::AsRawFd, time::Duration};
+        let xy = Box::new([3.0f64, 4.0]);
+        let pid = unsafe { KWinPid::from(std::process::id() as i32) };
+        let mouse = unsafe { Mouse::from_raw(pid, xy.as_ptr() as *mut std::ffi::c_void) };
+        let mut stream = stream::PositionStream::new(mouse, Duration::from_millis(5)).unwrap();
+        assert!(stream.as_raw_fd() >= 0);
+        let first = stream.next_blocking(Some(Duration::from_secs(1)));
+        assert_eq!(first.unwrap().unwrap().pos, (3.0, 4.0));
+        // the position does not change, thus nothing is reported.
+        assert!(
+            stream
+                .next_blocking(Some(Duration::from_millis(30)))
+                .is_none()
+        );
+        // a zero interval still fires.
+        let mut stream = stream::PositionStream::new(stream.into_inner(), Duration::ZERO).unwrap();
+        let first = stream.next_blocking(Some(Duration::from_secs(1)));
+        assert_eq!(first.unwrap().unwrap().pos, (3.0, 4.0));
+    }
+    #[test]
+    fn validate_fake_workspace() {
+        use pointer::{Check, Rect};
+        use std::ffi::c_void;
+        let pos = unsafe { consts::POS_OFFSET };
+        let mut object = vec![0u8; pos + 16];
+        object[pos..pos + 8].copy_from_slice(&100f64.to_ne_bytes());
+        object[pos + 8..].copy_from_slice(&200f64.to_ne_bytes());
+        let this = Box::new(object.as_mut_ptr() as *mut c_void);
+        let pid = unsafe { KWinPid::from(std::process::id() as i32) };
+        let workspace = unsafe { Workspace::from_raw(pid, &*this as *const _ as *mut c_void) };
+        let report = workspace.validate(&[Rect::new(0.0, 0.0, 1920.0, 1080.0)]);
+        assert_eq!(report.pos, Some((100.0, 200.0)));
+        assert_eq!(report.heap, Check::Passed);
+        assert_eq!(report.outputs, Check::Passed);
+        assert!(matches!(report.movement, Check::Skipped(_)));
+        assert!(report.trusted());
+        let report = workspace.validate(&[Rect::new(1920.0, 0.0, 1920.0, 1080.0)]);
+        assert!(matches!(report.outputs, Check::Failed(_)) && !report.trusted());
+        object[pos..pos + 8].copy_from_slice(&f64::NAN.to_ne_bytes());
+        let report = workspace.validate(&[]);
+        assert!(matches!(report.finite, Check::Failed(_)));
+        assert!(report.confidence() < 0.5);
+    }
 }
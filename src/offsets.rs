@@ -0,0 +1,262 @@
+// This file only relies on `std` and `elf`.
+use std::{
+    collections::BTreeMap,
+    env,
+    fmt::Display,
+    fs, io,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// the cache file used when `XDG_CACHE_HOME` is not set.
+pub const SYSTEM_CACHE: &str = "/var/cache/kwin-mouse-loc/offsets";
+
+/// the cache file, `$XDG_CACHE_HOME/kwin-mouse-loc/offsets` if `XDG_CACHE_HOME` is set, otherwise `SYSTEM_CACHE`.
+pub fn cache_path() -> PathBuf {
+    match env::var_os("XDG_CACHE_HOME").filter(|x| !x.is_empty()) {
+        Some(x) => PathBuf::from(x).join("kwin-mouse-loc/offsets"),
+        None => PathBuf::from(SYSTEM_CACHE),
+    }
+}
+
+/// the history of offset updates used when `XDG_CACHE_HOME` is not set.
+pub const SYSTEM_HISTORY: &str = "/var/cache/kwin-mouse-loc/history";
+
+/// the history of offset updates, next to `cache_path()`.
+pub fn history_path() -> PathBuf {
+    match env::var_os("XDG_CACHE_HOME").filter(|x| !x.is_empty()) {
+        Some(x) => PathBuf::from(x).join("kwin-mouse-loc/history"),
+        None => PathBuf::from(SYSTEM_HISTORY),
+    }
+}
+
+/// how an entry of `Database` is matched against the mapped libkwin.
+#[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+pub enum Key {
+    /// the GNU build-id of libkwin, in lowercase hex.
+    BuildId(String),
+    /// the version suffix of the soname, e.g., `6.1.4` for `libkwin.so.6.1.4`.
+    Version(String),
+}
+impl Key {
+    /// key of a raw build-id.
+    pub fn build_id(id: &[u8]) -> Self {
+        Self::BuildId(id.iter().map(|x| format!("{x:02x}")).collect())
+    }
+    /// key of the libkwin at `path`: its build-id, or the version suffix of its real file name without build-id.
+    pub fn of_library(path: impl AsRef<Path>) -> Result<Self, crate::elf::Error> {
+        if let Some(id) = crate::elf::Elf::open(&path)?.build_id() {
+            return Ok(Self::build_id(id));
+        }
+        fs::canonicalize(path)?
+            .file_name()
+            .and_then(|x| x.to_str()?.split_once(".so."))
+            .map(|x| Self::Version(x.1.to_owned()))
+            .ok_or(crate::elf::Error::Unsupported(
+                "neither build-id nor version suffix is found",
+            ))
+    }
+}
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BuildId(x) => write!(f, "build-id.{x}"),
+            Self::Version(x) => write!(f, "version.\"{x}\""),
+        }
+    }
+}
+
+/// offsets of one libkwin.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Entry {
+    /// offset of `KWin::Workspace::_self` in libkwin.
+    pub workspace: Option<usize>,
+    /// offset of `focusMousePos` in `KWin::Workspace`.
+    pub pos: Option<usize>,
+    /// other field offsets, by name.
+    pub fields: BTreeMap<String, usize>,
+}
+
+/// errors of loading a `Database`.
+#[derive(Debug)]
+pub enum Error {
+    /// the file cannot be read.
+    Io(io::Error),
+    /// a line cannot be parsed, with its line number (from 1).
+    Parse(usize, String),
+}
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "cannot read offset database: {e}"),
+            Self::Parse(line, x) => write!(f, "offset database line {line}: {x}"),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// offsets of several libkwin versions, in a TOML-like text format.
+///
+/// ```text
+/// # KWin 6.1.4 on Arch
+/// [build-id.3c0ffee0ddba11]
+/// workspace = 0x6a5f80
+/// pos = 176
+///
+/// # any build of KWin 6.0.5
+/// [version."6.0.5"]
+/// workspace = 0x69c3a0
+/// pos = 176
+/// activeWindow = 0x98
+/// ```
+///
+/// `workspace` and `pos` are the two offsets used by this crate, other keys are kept in `Entry::fields`.
+/// Numbers are decimal or `0x` prefixed hex, `#` starts a comment.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Database {
+    entries: BTreeMap<Key, Entry>,
+}
+impl Database {
+    /// an empty database.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// read and parse the file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::parse(&fs::read_to_string(path).map_err(Error::Io)?)
+    }
+    /// like `load`, but a missing file gives an empty database.
+    pub fn load_or_empty(path: impl AsRef<Path>) -> Result<Self, Error> {
+        match Self::load(path) {
+            Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound => Ok(Self::new()),
+            x => x,
+        }
+    }
+    /// load the cache file at `cache_path()`, a missing file gives an empty database.
+    pub fn load_cache() -> Result<Self, Error> {
+        Self::load_or_empty(cache_path())
+    }
+    /// write to `path` atomically: the content is written to a temporary file in the same directory, then renamed.
+    ///
+    /// The parent directories are created if needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(Error::Io)?;
+        }
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(format!(".{}.tmp", std::process::id()));
+        let write = || -> io::Result<()> {
+            let mut file = fs::File::create(&tmp)?;
+            file.write_all(self.to_string().as_bytes())?;
+            file.sync_all()?;
+            fs::rename(&tmp, path)
+        };
+        write().map_err(|e| {
+            let _ = fs::remove_file(&tmp);
+            Error::Io(e)
+        })
+    }
+    /// parse the text format, see `Database`.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut ret = Self::new();
+        let mut current = None;
+        for (i, line) in text.lines().enumerate() {
+            let err = |x: &str| Error::Parse(i + 1, x.to_owned());
+            let line = line.split_once('#').map_or(line, |x| x.0).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[') {
+                let header = header.strip_suffix(']').ok_or_else(|| err("missing `]`"))?;
+                let key = match header.trim().split_once('.') {
+                    Some(("build-id", x))
+                        if !x.is_empty() && x.bytes().all(|x| x.is_ascii_hexdigit()) =>
+                    {
+                        Key::BuildId(x.to_ascii_lowercase())
+                    }
+                    Some(("version", x)) => Key::Version(x.trim_matches('"').to_owned()),
+                    _ => {
+                        return Err(err(
+                            "expect `[build-id.<hex>]` or `[version.\"<version>\"]`",
+                        ));
+                    }
+                };
+                ret.entries.entry(key.clone()).or_default();
+                current = Some(key);
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| err("expect `name = offset`"))?;
+            let value = value.trim();
+            let value = match value.strip_prefix("0x") {
+                Some(x) => usize::from_str_radix(x, 16),
+                None => value.parse(),
+            }
+            .map_err(|_| err("offset is not a number"))?;
+            let entry = current
+                .as_ref()
+                .and_then(|x| ret.entries.get_mut(x))
+                .ok_or_else(|| err("offset outside of any section"))?;
+            match name.trim() {
+                "workspace" => entry.workspace = Some(value),
+                "pos" => entry.pos = Some(value),
+                x => {
+                    entry.fields.insert(x.to_owned(), value);
+                }
+            }
+        }
+        Ok(ret)
+    }
+    /// the entry of `key`.
+    pub fn get(&self, key: &Key) -> Option<&Entry> {
+        self.entries.get(key)
+    }
+    /// insert or replace the entry of `key`.
+    pub fn insert(&mut self, key: Key, entry: Entry) -> Option<Entry> {
+        self.entries.insert(key, entry)
+    }
+    /// remove the entry of `key`.
+    pub fn remove(&mut self, key: &Key) -> Option<Entry> {
+        self.entries.remove(key)
+    }
+    /// all entries.
+    pub fn entries(&self) -> impl Iterator<Item = (&Key, &Entry)> {
+        self.entries.iter()
+    }
+    /// the entry matching `build_id`, or `version` if no build-id matches.
+    pub fn lookup(&self, build_id: Option<&[u8]>, version: Option<&str>) -> Option<&Entry> {
+        build_id
+            .and_then(|x| self.get(&Key::build_id(x)))
+            .or_else(|| version.and_then(|x| self.get(&Key::Version(x.to_owned()))))
+    }
+}
+/// render in the format accepted by `Database::parse`.
+impl Display for Database {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (key, entry)) in self.entries.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "[{key}]")?;
+            if let Some(x) = entry.workspace {
+                writeln!(f, "workspace = 0x{x:x}")?;
+            }
+            if let Some(x) = entry.pos {
+                writeln!(f, "pos = {x}")?;
+            }
+            for (name, x) in &entry.fields {
+                writeln!(f, "{name} = 0x{x:x}")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+// included in `consts`.
+
+/// the sentinel values of `.kwin.mouse.loc.offset` in a binary that has never been patched by `readelf`.
+pub const SENTINEL: [usize; 2] = [0xfacefeedcafebabe, 0xdeadbeeffee1dead];
+
+/// errors of `try_save_offset` and `patch_offset`.
+#[derive(Debug)]
+pub enum SaveError {
+    /// an io operation failed, with what was done and the file.
+    Io(&'static str, std::path::PathBuf, std::io::Error),
+    /// the executable cannot be parsed.
+    Elf(crate::elf::Error),
+    /// the executable has no such section (it is not linked with this crate?)
+    MissingSection(&'static str),
+    /// `.kwin.mouse.loc.offset` holds neither the sentinels nor the section offsets, with its content.
+    SentinelMismatch([usize; 3]),
+}
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(what, path, e) => write!(f, "cannot {what} {}: {e}", path.display()),
+            Self::Elf(e) => write!(f, "{e}"),
+            Self::MissingSection(x) => write!(f, "section {x} is not found"),
+            Self::SentinelMismatch([a, b, c]) => write!(
+                f,
+                "value mismatch! The offset might be incorrect. (0x{a:x} 0x{b:x} 0x{c:x})"
+            ),
+        }
+    }
+}
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(_, _, e) => Some(e),
+            Self::Elf(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// how `patch_offset` wrote the file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Patched {
+    /// the file is written in place.
+    InPlace,
+    /// the file cannot be opened for writing (e.g., `ETXTBSY`), a patched copy is renamed over it.
+    Copy,
+}
+
+impl Offset {
+    /// name of the section holding the offset.
+    pub fn section(&self) -> &'static str {
+        match self {
+            Self::Offset => ".kwin.mouse.loc.offset",
+            Self::Pos => ".kwin.mouse.loc.pos",
+            Self::Kwin => ".kwin.mouse.loc.kwin",
+        }
+    }
+}
+
+/// write `val` into the section of `item` in the ELF file at `path`, located by its section headers.
+///
+/// `.kwin.mouse.loc.offset` should hold the sentinels (or the section offsets, written by older versions
+/// through `readelf`), otherwise nothing is written.
+pub fn patch_offset(path: &Path, val: usize, item: Offset) -> Result<Patched, SaveError> {
+    use std::io::{Seek, SeekFrom, Write};
+    let io = |what| move |e| SaveError::Io(what, path.to_owned(), e);
+    let elf = crate::elf::Elf::open(path).map_err(|e| match e {
+        crate::elf::Error::Io(e) => io("read")(e),
+        e => SaveError::Elf(e),
+    })?;
+    let offset_of = |item: &Offset| {
+        elf.section(item.section())
+            .filter(|x| x.size >= 8)
+            .map(|x| x.offset as usize)
+            .ok_or(SaveError::MissingSection(item.section()))
+    };
+    let offsets = [
+        offset_of(&Offset::Offset)?,
+        offset_of(&Offset::Pos)?,
+        offset_of(&Offset::Kwin)?,
+    ];
+    let header = elf
+        .bytes(offsets[0] as u64, 24)
+        .map_err(SaveError::Elf)?
+        .chunks(8)
+        .map(|x| usize::from_ne_bytes(x.try_into().unwrap()))
+        .collect::<Vec<_>>();
+    let header = [header[0], header[1], header[2]];
+    if header[1..] != SENTINEL && header != offsets {
+        return Err(SaveError::SentinelMismatch(header));
+    }
+    let target = offset_of(&item)? as u64;
+    let write = |file: &mut fs::File| -> std::io::Result<()> {
+        file.seek(SeekFrom::Start(target))?;
+        file.write_all(&val.to_ne_bytes())?;
+        file.sync_all()
+    };
+    if let Ok(mut file) = OpenOptions::new().write(true).open(path) {
+        write(&mut file).map_err(io("write"))?;
+        return Ok(Patched::InPlace);
+    }
+    // the running executable cannot be written (ETXTBSY), but could be replaced.
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    let tmp = std::path::PathBuf::from(tmp);
+    let copy = || -> Result<(), SaveError> {
+        fs::copy(path, &tmp).map_err(|e| SaveError::Io("copy to", tmp.clone(), e))?;
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(&tmp)
+            .map_err(|e| SaveError::Io("open", tmp.clone(), e))?;
+        write(&mut file).map_err(|e| SaveError::Io("write", tmp.clone(), e))?;
+        fs::rename(&tmp, path).map_err(io("replace"))
+    };
+    copy().inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })?;
+    Ok(Patched::Copy)
+}
+
+/// fallible version of `save_offset`, the current executable is patched by `patch_offset`.
+///
+/// SAFETY: the executable is modified, it should not be executed concurrently.
+pub unsafe fn try_save_offset(val: usize, item: Offset) -> Result<Patched, SaveError> {
+    let exe = env::current_exe().map_err(|e| SaveError::Io("find", "current exe".into(), e))?;
+    // SAFETY: only read, it also keeps the section from being dropped by the linker.
+    let header = unsafe { OFFSET };
+    if header[1..] != SENTINEL && header[0] == 0 {
+        return Err(SaveError::SentinelMismatch(header));
+    }
+    patch_offset(&exe, val, item)
+}
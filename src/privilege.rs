@@ -0,0 +1,174 @@
+use crate::pointer::Error;
+use std::{fmt::Display, fs, os::unix::fs::MetadataExt, path::PathBuf};
+
+/// bit of `CAP_SYS_PTRACE` in the capability masks of `/proc/{pid}/status`.
+const CAP_SYS_PTRACE: u32 = 19;
+
+/// what `process_vm_readv` on the target requires, and what the caller currently has.
+///
+/// `process_vm_readv` is checked with `PTRACE_MODE_ATTACH_REALCREDS`: the caller either has the same uid as
+/// the target or holds `CAP_SYS_PTRACE`, and Yama's `ptrace_scope` must allow the attach.
+/// The same uid is not enough for a non-dumpable target (kwin_wayland calls `prctl(PR_SET_DUMPABLE, 0)`),
+/// `CAP_SYS_PTRACE` is required then. The check happens on every call, not when the handles are opened.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Report {
+    /// pid of the target.
+    pub pid: i32,
+    /// real, effective and saved uid of the caller.
+    pub uid: [u32; 3],
+    /// real, effective and saved uid of the target, `None` if its status cannot be read.
+    pub target_uid: Option<[u32; 3]>,
+    /// whether the target is dumpable, `None` if it cannot be told.
+    ///
+    /// `/proc/{pid}` of a non-dumpable process is owned by root, while its uid is not.
+    pub dumpable: Option<bool>,
+    /// whether `CAP_SYS_PTRACE` is in the effective set of the caller.
+    pub cap_sys_ptrace: bool,
+    /// whether `CAP_SYS_PTRACE` is in the permitted set of the caller (i.e., could be raised).
+    pub cap_sys_ptrace_permitted: bool,
+    /// value of `/proc/sys/kernel/yama/ptrace_scope`, `None` if Yama is not enabled.
+    pub ptrace_scope: Option<u8>,
+}
+/// the verdict of a `Report`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Access {
+    /// the read should succeed with the current credentials.
+    Granted,
+    /// the read will fail, but `escalate` could help (saved uid is 0 or `CAP_SYS_PTRACE` is permitted).
+    NeedsEscalation,
+    /// the read will fail, and nothing in this process could change that.
+    Denied,
+}
+impl Report {
+    /// check the access to `pid` through `/proc`.
+    pub fn check(pid: i32) -> Self {
+        Self::check_with_root("/proc", pid)
+    }
+    /// check the access to `pid` using a custom `/proc` root.
+    pub fn check_with_root(root: impl Into<PathBuf>, pid: i32) -> Self {
+        let root = root.into();
+        let own = fs::read_to_string(root.join("self/status")).unwrap_or_default();
+        let target = fs::read_to_string(root.join(pid.to_string()).join("status")).ok();
+        let cap = |key| {
+            field(&own, key)
+                .and_then(|x| u64::from_str_radix(x.trim(), 16).ok())
+                .is_some_and(|x| x >> CAP_SYS_PTRACE & 1 == 1)
+        };
+        let target_uid = target.as_deref().and_then(uids);
+        let owner = fs::metadata(root.join(pid.to_string())).map(|x| x.uid());
+        Self {
+            pid,
+            uid: uids(&own).unwrap_or_else(|| unsafe {
+                let (mut r, mut e, mut s) = (0, 0, 0);
+                libc::getresuid(&mut r, &mut e, &mut s);
+                [r, e, s]
+            }),
+            target_uid,
+            dumpable: match (owner, target_uid) {
+                (Ok(owner), Some(t)) => Some(owner != 0 || t[1] == 0),
+                _ => None,
+            },
+            cap_sys_ptrace: cap("CapEff:"),
+            cap_sys_ptrace_permitted: cap("CapPrm:"),
+            ptrace_scope: fs::read_to_string(root.join("sys/kernel/yama/ptrace_scope"))
+                .ok()
+                .and_then(|x| x.trim().parse().ok()),
+        }
+    }
+    /// whether the real, effective and saved uid of the target all match the real uid of the caller.
+    pub fn same_uid(&self) -> bool {
+        self.target_uid
+            .is_some_and(|t| t.iter().all(|x| *x == self.uid[0]))
+    }
+    /// summarize the report, the same uid only counts for a target known to be dumpable.
+    pub fn access(&self) -> Access {
+        let allowed = |cap: bool, same_uid: bool| match self.ptrace_scope {
+            // no attach at all.
+            Some(3) => false,
+            // admin-only attach.
+            Some(2) => cap,
+            // restricted attach, kwin_wayland is never our descendant.
+            Some(1) => cap,
+            _ => cap || same_uid,
+        };
+        let same_uid = self.same_uid() && self.dumpable == Some(true);
+        if allowed(self.cap_sys_ptrace, same_uid) {
+            Access::Granted
+        } else if allowed(
+            self.cap_sys_ptrace_permitted || self.uid[2] == 0 || self.uid[0] == 0,
+            false,
+        ) {
+            Access::NeedsEscalation
+        } else {
+            Access::Denied
+        }
+    }
+}
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pid {}: uid {:?}, target uid {:?}, dumpable {:?}, CAP_SYS_PTRACE {}/{}, ptrace_scope {:?} => {:?}",
+            self.pid,
+            self.uid,
+            self.target_uid,
+            self.dumpable,
+            self.cap_sys_ptrace,
+            self.cap_sys_ptrace_permitted,
+            self.ptrace_scope,
+            self.access()
+        )
+    }
+}
+fn field<'a>(status: &'a str, key: &str) -> Option<&'a str> {
+    status.lines().find_map(|x| x.strip_prefix(key))
+}
+fn uids(status: &str) -> Option<[u32; 3]> {
+    let mut it = field(status, "Uid:")?
+        .split_whitespace()
+        .map(|x| x.parse().ok());
+    Some([it.next()??, it.next()??, it.next()??])
+}
+
+/// the credentials before `escalate`, which could be restored later.
+///
+/// Dropping it without calling `restore` or `drop_permanently` keeps the escalated credentials.
+#[derive(Debug)]
+#[must_use = "the escalated credentials are kept until `restore` or `drop_permanently` is called"]
+pub struct Escalation {
+    uid: [u32; 3],
+}
+/// explicitly become root (effective uid 0), which is the only place this crate changes its credentials.
+///
+/// This works when the caller is root or the executable is setuid-root. Since only the effective uid is changed,
+/// the original credentials could be restored by the returned `Escalation`.
+pub fn escalate() -> Result<Escalation, Error> {
+    let mut uid = [0; 3];
+    unsafe {
+        libc::getresuid(&mut uid[0], &mut uid[1], &mut uid[2]);
+        if uid[1] != 0 && libc::seteuid(0) != 0 {
+            return Err(Error::last_errno());
+        }
+    }
+    Ok(Escalation { uid })
+}
+impl Escalation {
+    /// go back to the effective uid before `escalate`, the saved uid is kept so `escalate` could be called again.
+    ///
+    /// Note that `process_vm_readv` checks the credentials on every call, `Mouse::loc` will fail afterwards unless
+    /// the report of the target is `Access::Granted` with the restored credentials.
+    pub fn restore(self) -> Result<(), Error> {
+        match unsafe { libc::seteuid(self.uid[1]) } {
+            0 => Ok(()),
+            _ => Err(Error::last_errno()),
+        }
+    }
+    /// set all of real, effective and saved uid to the real uid before `escalate`, which cannot be undone.
+    pub fn drop_permanently(self) -> Result<(), Error> {
+        let uid = self.uid[0];
+        match unsafe { libc::setresuid(uid, uid, uid) } {
+            0 => Ok(()),
+            _ => Err(Error::last_errno()),
+        }
+    }
+}
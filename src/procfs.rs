@@ -0,0 +1,329 @@
+use crate::elf::Elf;
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// a process that looks like kwin_wayland.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Candidate {
+    /// pid of the process.
+    pub pid: i32,
+    /// real uid of the process, read from `/proc/{pid}/status`.
+    pub uid: u32,
+    /// start time of the process, in clock ticks since boot (field 22 of `/proc/{pid}/stat`).
+    pub start_time: u64,
+    /// audit (login) session id from `/proc/{pid}/sessionid`, `u32::MAX` if unset.
+    pub session: u32,
+    /// target of `/proc/{pid}/exe`, `None` if it cannot be read (require permissions?)
+    pub exe: Option<PathBuf>,
+    /// content of `/proc/{pid}/comm`.
+    pub comm: String,
+    /// arguments from `/proc/{pid}/cmdline`.
+    pub cmdline: Vec<String>,
+}
+impl Candidate {
+    /// read the information of `pid` from `root` (which is often `/proc`).
+    ///
+    /// returns `Ok(None)` if the process is not kwin_wayland.
+    pub fn read(root: &Path, pid: i32) -> io::Result<Option<Self>> {
+        let dir = root.join(pid.to_string());
+        let exe = fs::read_link(dir.join("exe")).ok();
+        let comm = fs::read_to_string(dir.join("comm"))?.trim_end().to_owned();
+        let cmdline = fs::read(dir.join("cmdline"))?
+            .split(|&x| x == 0)
+            .filter(|x| !x.is_empty())
+            .map(|x| String::from_utf8_lossy(x).into_owned())
+            .collect::<Vec<_>>();
+        let is_kwin = |x: &Path| x.file_name().is_some_and(|x| x == "kwin_wayland");
+        if !(exe.as_deref().is_some_and(is_kwin)
+            || comm == "kwin_wayland"
+            || cmdline.first().is_some_and(|x| is_kwin(Path::new(x))))
+        {
+            return Ok(None);
+        }
+        let invalid = |what| {
+            io::Error::new(
+                ErrorKind::InvalidData,
+                format!("cannot parse {what} of pid {pid}"),
+            )
+        };
+        let uid = fs::read_to_string(dir.join("status"))?
+            .lines()
+            .find_map(|x| x.strip_prefix("Uid:"))
+            .and_then(|x| x.split_whitespace().next()?.parse().ok())
+            .ok_or_else(|| invalid("status"))?;
+        // comm might contain spaces and parens, fields are counted after the last `)`.
+        let start_time = fs::read_to_string(dir.join("stat"))?
+            .rsplit_once(')')
+            .and_then(|x| x.1.split_whitespace().nth(19)?.parse().ok())
+            .ok_or_else(|| invalid("stat"))?;
+        let session = read_session(&dir)?;
+        Ok(Some(Self {
+            pid,
+            uid,
+            start_time,
+            session,
+            exe,
+            comm,
+            cmdline,
+        }))
+    }
+    /// whether `libkwin.so` appears in `/proc/{pid}/maps`.
+    pub fn has_libkwin(&self, root: &Path) -> bool {
+        read_maps(root, self.pid).is_ok_and(|x| x.iter().any(|x| Library::default().matches(x)))
+    }
+}
+fn read_session(dir: &Path) -> io::Result<u32> {
+    match fs::read_to_string(dir.join("sessionid")) {
+        Ok(x) => Ok(x.trim().parse().unwrap_or(u32::MAX)),
+        // kernels without audit support do not provide sessionid.
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(u32::MAX),
+        Err(e) => Err(e),
+    }
+}
+
+/// filters for `Scanner`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Filter {
+    /// owned by the given (real) uid.
+    Uid(u32),
+    /// in the same login session as the caller (`{root}/self/sessionid`).
+    SameSession,
+    /// has libkwin.so mapped, reading maps may require permissions.
+    LibkwinMapped,
+}
+
+/// walks `/proc` (or a fake root for tests) and collects every kwin_wayland candidate.
+///
+/// ```no_run
+/// use kwin_mouse_loc::procfs::{Filter, Scanner};
+/// let candidates = Scanner::new().filter(Filter::SameSession).scan().unwrap();
+/// for c in candidates {
+///     println!("{} (uid {}) started at {}", c.pid, c.uid, c.start_time);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Scanner {
+    root: PathBuf,
+    filters: Vec<Filter>,
+}
+impl Default for Scanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl Scanner {
+    /// scan `/proc` without filters.
+    pub fn new() -> Self {
+        Self::with_root("/proc")
+    }
+    /// scan a custom root, which should have the same layout as `/proc`.
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            filters: Vec::new(),
+        }
+    }
+    /// add a filter, all filters must match.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+    /// the root being scanned.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+    /// collect all candidates that pass the filters, sorted by pid.
+    ///
+    /// Processes that exit (or cannot be read) during scanning are skipped silently.
+    pub fn scan(&self) -> io::Result<Vec<Candidate>> {
+        let session = if self.filters.contains(&Filter::SameSession) {
+            Some(read_session(&self.root.join("self"))?)
+        } else {
+            None
+        };
+        let mut ret = fs::read_dir(&self.root)?
+            .filter_map(|x| x.ok()?.file_name().to_str()?.parse::<i32>().ok())
+            .filter_map(|pid| Candidate::read(&self.root, pid).ok().flatten())
+            .filter(|c| {
+                self.filters.iter().all(|f| match f {
+                    Filter::Uid(uid) => c.uid == *uid,
+                    Filter::SameSession => Some(c.session) == session && c.session != u32::MAX,
+                    Filter::LibkwinMapped => c.has_libkwin(&self.root),
+                })
+            })
+            .collect::<Vec<_>>();
+        ret.sort_by_key(|c| c.pid);
+        Ok(ret)
+    }
+}
+
+/// a line of `/proc/{pid}/maps`.
+///
+/// ```text
+/// 70642a400000-70642a54a000 r--p 00000000 103:02 3323906                   /usr/lib/libkwin.so.6.1.4
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mapping {
+    /// start address (inclusive).
+    pub start: usize,
+    /// end address (exclusive).
+    pub end: usize,
+    /// permissions, e.g., `r-xp`.
+    pub perms: String,
+    /// offset into the mapped file.
+    pub offset: u64,
+    /// device (major, minor) of the mapped file.
+    pub dev: (u32, u32),
+    /// inode of the mapped file, 0 for anonymous mappings.
+    pub inode: u64,
+    /// path of the mapped file or pseudo-path such as `[heap]`, `None` for anonymous mappings.
+    pub path: Option<String>,
+}
+impl Mapping {
+    /// parse a line of `/proc/{pid}/maps`.
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut it = line.splitn(6, ' ');
+        let (start, end) = it.next()?.split_once('-')?;
+        let perms = it.next()?.to_owned();
+        let offset = u64::from_str_radix(it.next()?, 16).ok()?;
+        let (major, minor) = it.next()?.split_once(':')?;
+        let inode = it.next()?.parse().ok()?;
+        // the path is padded with spaces, and might contain spaces itself.
+        let path = it.next().map(str::trim_start).filter(|x| !x.is_empty());
+        Some(Self {
+            start: usize::from_str_radix(start, 16).ok()?,
+            end: usize::from_str_radix(end, 16).ok()?,
+            perms,
+            offset,
+            dev: (
+                u32::from_str_radix(major, 16).ok()?,
+                u32::from_str_radix(minor, 16).ok()?,
+            ),
+            inode,
+            path: path.map(ToOwned::to_owned),
+        })
+    }
+    /// whether the mapping is readable.
+    pub fn readable(&self) -> bool {
+        self.perms.starts_with('r')
+    }
+    /// whether the mapping is writable.
+    pub fn writable(&self) -> bool {
+        self.perms.as_bytes().get(1) == Some(&b'w')
+    }
+    /// whether the mapping holds heap memory, i.e., `[heap]` or an anonymous writable mapping (malloc arenas).
+    pub fn heap(&self) -> bool {
+        self.writable() && self.inode == 0 && matches!(self.path.as_deref(), None | Some("[heap]"))
+    }
+    /// whether the mapping is executable.
+    pub fn executable(&self) -> bool {
+        self.perms.as_bytes().get(2) == Some(&b'x')
+    }
+    /// whether the mapped file is deleted (e.g., replaced by a package upgrade).
+    pub fn deleted(&self) -> bool {
+        self.path
+            .as_deref()
+            .is_some_and(|x| x.ends_with(" (deleted)"))
+    }
+    /// the mapped file name without directories.
+    pub fn file_name(&self) -> Option<&str> {
+        let path = self.path.as_deref()?;
+        let path = path.strip_suffix(" (deleted)").unwrap_or(path);
+        Some(path.rsplit_once('/').map_or(path, |x| x.1))
+    }
+    /// the version suffix of the file name, e.g., `6.1.4` of `libkwin.so.6.1.4`.
+    pub fn version(&self) -> Option<&str> {
+        self.file_name()?.split_once(".so.").map(|x| x.1)
+    }
+}
+/// parse the content of `/proc/{pid}/maps`, lines that cannot be parsed are ignored.
+pub fn parse_maps(maps: &str) -> Vec<Mapping> {
+    maps.lines().filter_map(Mapping::parse).collect()
+}
+/// read and parse `{root}/{pid}/maps`.
+pub fn read_maps(root: &Path, pid: i32) -> io::Result<Vec<Mapping>> {
+    Ok(parse_maps(&fs::read_to_string(
+        root.join(pid.to_string()).join("maps"),
+    )?))
+}
+
+/// which mapped library should be used.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Library {
+    /// file name is `name` with an optional version suffix (e.g. `libkwin.so` matches `libkwin.so.6.1.4`).
+    Name(String),
+    /// the exact path of the library, as shown in maps.
+    Path(PathBuf),
+    /// the inode of the library.
+    Inode(u64),
+}
+impl Default for Library {
+    fn default() -> Self {
+        Self::Name("libkwin.so".to_owned())
+    }
+}
+impl Library {
+    /// whether `mapping` maps this library.
+    pub fn matches(&self, mapping: &Mapping) -> bool {
+        mapping.inode != 0
+            && match self {
+                Self::Name(name) => mapping.file_name().is_some_and(|x| {
+                    // only version suffixes are allowed, e.g. `.6` or `.6.1.4`.
+                    x.strip_prefix(name.as_str()).is_some_and(|x| {
+                        x.is_empty()
+                            || x.strip_prefix('.').is_some_and(|v| {
+                                !v.is_empty() && v.bytes().all(|b| b.is_ascii_digit() || b == b'.')
+                            })
+                    })
+                }),
+                Self::Path(path) => mapping
+                    .path
+                    .as_deref()
+                    .is_some_and(|x| Path::new(x) == path),
+                Self::Inode(inode) => mapping.inode == *inode,
+            }
+    }
+    /// all mappings of this library, they must belong to exactly one file (device and inode).
+    ///
+    /// returns `Err` with all matched paths if more than one file matches.
+    pub fn select<'a>(&self, maps: &'a [Mapping]) -> Result<Vec<&'a Mapping>, Vec<String>> {
+        let ret = maps.iter().filter(|x| self.matches(x)).collect::<Vec<_>>();
+        match ret.first() {
+            Some(first)
+                if ret
+                    .iter()
+                    .any(|x| (x.dev, x.inode) != (first.dev, first.inode)) =>
+            {
+                let mut paths = ret
+                    .iter()
+                    .filter_map(|x| x.path.clone())
+                    .collect::<Vec<_>>();
+                paths.dedup();
+                Err(paths)
+            }
+            _ => Ok(ret),
+        }
+    }
+}
+
+/// compute the load bias of the library mapped by `maps`, using its lowest `PT_LOAD` segment.
+///
+/// All mappings should belong to the same file (see `Library::select`).
+pub fn load_bias(maps: &[&Mapping], elf: &Elf) -> Option<usize> {
+    let page = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        x if x > 0 => x as u64,
+        _ => 4096,
+    };
+    let load = elf.lowest_load()?;
+    let offset = load.offset & !(page - 1);
+    let mapping = maps
+        .iter()
+        .filter(|x| x.offset == offset)
+        .min_by_key(|x| x.start)?;
+    (mapping.start as u64)
+        .checked_sub(load.vaddr & !(page - 1))
+        .map(|x| x as usize)
+}
@@ -0,0 +1,93 @@
+// included in `pointer`.
+
+/// where the offsets found by `Workspace::try_resolve` come from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Source {
+    /// an entry of `offsets::Database`, matched by build-id or version.
+    Database,
+    /// the compiled-in `WORKSPACE_OFFSET` and `POS_OFFSET`.
+    Compiled,
+    /// the symbol table (and debug info, if installed) of the mapped libkwin.
+    Live,
+}
+
+/// result of `Workspace::try_resolve`.
+pub struct Resolved {
+    /// the located workspace.
+    pub workspace: Workspace,
+    /// the located mouse.
+    pub mouse: Mouse,
+    /// where the offsets come from.
+    pub source: Source,
+    /// offset of `KWin::Workspace::_self` in libkwin.
+    pub workspace_offset: usize,
+    /// offset of `focusMousePos` in `KWin::Workspace`.
+    pub pos_offset: usize,
+    /// build-id of the mapped libkwin, in lowercase hex.
+    pub build_id: Option<String>,
+    /// version suffix of the mapped libkwin, e.g., `6.1.4`.
+    pub version: Option<String>,
+}
+
+impl Workspace {
+    /// locate the workspace and mouse of `pid` with the entry of `database` matching the mapped libkwin.
+    ///
+    /// Without a matching entry, the compiled-in constants are used if they are built for the mapped libkwin (see
+    /// `consts::BUILD_INFO`) and `validate` trusts them, otherwise the offsets are detected from the mapped libkwin
+    /// (symbol table, and `focusMousePos` from its debug info).
+    pub fn try_resolve(
+        pid: KWinPid,
+        database: &crate::offsets::Database,
+    ) -> Result<Resolved, Error> {
+        Self::try_resolve_with(pid, database, &Library::default())
+    }
+    /// like `try_resolve`, but the mapped libkwin is selected by `library`.
+    pub fn try_resolve_with(
+        pid: KWinPid,
+        database: &crate::offsets::Database,
+        library: &Library,
+    ) -> Result<Resolved, Error> {
+        let (base, elf, mapping) = Self::load_libkwin(pid, library)?;
+        let build_id = elf.build_id();
+        let version = mapping.version().map(str::to_owned);
+        let resolved = |workspace_offset: usize, pos_offset, source| -> Result<Resolved, Error> {
+            let workspace = Workspace(pid, (base + workspace_offset) as *mut c_void, pos_offset);
+            Ok(Resolved {
+                mouse: workspace.try_get_mouse()?,
+                workspace,
+                source,
+                workspace_offset,
+                pos_offset,
+                build_id: build_id.map(|x| x.iter().map(|x| format!("{x:02x}")).collect()),
+                version: version.clone(),
+            })
+        };
+        // SAFETY: the compiled-in constants are only read.
+        let (compiled_workspace, compiled_pos) = unsafe { (WORKSPACE_OFFSET, POS_OFFSET) };
+        if let Some(entry) = database.lookup(build_id, version.as_deref())
+            && let Some(workspace_offset) = entry.workspace
+        {
+            return resolved(
+                workspace_offset,
+                entry.pos.unwrap_or(compiled_pos),
+                Source::Database,
+            );
+        }
+        if BUILD_INFO.matches(build_id) != Some(false)
+            && let Ok(x) = resolved(compiled_workspace, compiled_pos, Source::Compiled)
+        {
+            let report = x.workspace.validate(&[]);
+            if report.heap == Check::Passed && report.finite == Check::Passed {
+                return Ok(x);
+            }
+        }
+        let workspace_offset = elf.lookup(elf::WORKSPACE_SELF)?.value as usize;
+        let pos_offset = match mapping.path.as_deref() {
+            Some(path) if !mapping.deleted() => {
+                crate::dwarf::focus_mouse_pos_offset(path).unwrap_or(compiled_pos)
+            }
+            _ => compiled_pos,
+        };
+        resolved(workspace_offset, pos_offset, Source::Live)
+    }
+}
@@ -0,0 +1,188 @@
+// included in `pointer`.
+
+/// information passed to the hook of `Session::on_reattach`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Reattach {
+    /// pid of the previous kwin_wayland, `None` for the first attach.
+    pub old_pid: Option<i32>,
+    /// pid of the new kwin_wayland.
+    pub new_pid: i32,
+    /// start time of the new kwin_wayland (clock ticks since boot).
+    pub start_time: u64,
+}
+
+type ReattachHook = Box<dyn FnMut(&Reattach)>;
+
+struct Attached {
+    pidfd: Option<std::os::fd::OwnedFd>,
+    start_time: u64,
+    workspace: Workspace,
+    mouse: Mouse,
+}
+
+/// a `Workspace` and its `Mouse` that survive kwin_wayland restarts (e.g., `kwin_wayland --replace`).
+///
+/// The attached kwin_wayland is identified by a pidfd (`pidfd_open`) and its start time, thus a reused pid is
+/// never read. Once the process exits, discovery, `Workspace::get` and `get_mouse` are done again on the next read.
+///
+/// ```no_run
+/// use kwin_mouse_loc::{pointer::Session, procfs::Scanner};
+/// let mut session = Session::new(Scanner::new());
+/// session.on_reattach(|x| println!("attached to kwin_wayland {} (was {:?})", x.new_pid, x.old_pid));
+/// loop {
+///     match session.loc() {
+///         Ok((x, y)) => println!("({x}, {y})"),
+///         // kwin_wayland might be restarting.
+///         Err(e) => eprintln!("{e}"),
+///     }
+///     std::thread::sleep(std::time::Duration::from_millis(100));
+/// }
+/// ```
+pub struct Session {
+    scanner: Scanner,
+    workspace_offset: Option<usize>,
+    library: Library,
+    attached: Option<Attached>,
+    last_pid: Option<i32>,
+    hook: Option<ReattachHook>,
+}
+impl Session {
+    /// discover kwin_wayland with `scanner`, the attach is delayed until the first read.
+    ///
+    /// Like `Workspace::new`, the offset cache and `WORKSPACE_OFFSET` are read on every attach, thus an offset updated
+    /// meanwhile is used, and the attach fails with `Error::BuildMismatch` if the compiled-in offsets are for another
+    /// libkwin.
+    pub fn new(scanner: Scanner) -> Self {
+        Self::with_offset_opt(scanner, None)
+    }
+    /// like `new`, but always attach with `workspace_offset`, see `Workspace::get`.
+    pub fn with_offset(scanner: Scanner, workspace_offset: usize) -> Self {
+        Self::with_offset_opt(scanner, Some(workspace_offset))
+    }
+    fn with_offset_opt(scanner: Scanner, workspace_offset: Option<usize>) -> Self {
+        Self {
+            scanner,
+            workspace_offset,
+            library: Library::default(),
+            attached: None,
+            last_pid: None,
+            hook: None,
+        }
+    }
+    /// select the mapped libkwin by `library`, see `Workspace::try_get_with`.
+    pub fn library(mut self, library: Library) -> Self {
+        self.library = library;
+        self
+    }
+    /// call `hook` every time a kwin_wayland is attached, including the first time.
+    pub fn on_reattach(&mut self, hook: impl FnMut(&Reattach) + 'static) {
+        self.hook = Some(Box::new(hook));
+    }
+    /// whether the attached kwin_wayland is still running.
+    pub fn alive(&self) -> bool {
+        self.attached.as_ref().is_some_and(|x| x.alive())
+    }
+    /// the attached workspace, if any.
+    pub fn workspace(&self) -> Option<&Workspace> {
+        self.attached.as_ref().map(|x| &x.workspace)
+    }
+    /// the attached mouse, if any.
+    pub fn mouse(&self) -> Option<&Mouse> {
+        self.attached.as_ref().map(|x| &x.mouse)
+    }
+    /// (re-)attach now if the attached kwin_wayland exited, or nothing is attached.
+    pub fn ensure(&mut self) -> Result<&Mouse, Error> {
+        if !self.alive() {
+            self.attached = None;
+            self.attach()?;
+        }
+        Ok(&self.attached.as_ref().ok_or(Error::PidNotFound)?.mouse)
+    }
+    fn attach(&mut self) -> Result<(), Error> {
+        let mut last = Err(Error::PidNotFound);
+        for candidate in self.scanner.scan().map_err(Error::ProcUnreadable)? {
+            last = Attached::new(&candidate, self.workspace_offset, &self.library);
+            if let Ok(attached) = &last {
+                let info = Reattach {
+                    old_pid: self.last_pid,
+                    new_pid: candidate.pid,
+                    start_time: attached.start_time,
+                };
+                self.last_pid = Some(candidate.pid);
+                if let Some(hook) = self.hook.as_mut() {
+                    hook(&info)
+                }
+                break;
+            }
+        }
+        self.attached = Some(last?);
+        Ok(())
+    }
+    /// read the position, re-attach if kwin_wayland restarted.
+    pub fn loc(&mut self) -> Result<(f64, f64), Error> {
+        match self.ensure()?.try_loc() {
+            // the process exited between the liveness check and the read.
+            Err(Error::Errno(libc::ESRCH)) => {
+                self.attached = None;
+                self.ensure()?.try_loc()
+            }
+            x => x,
+        }
+    }
+}
+impl Attached {
+    fn new(
+        candidate: &procfs::Candidate,
+        offset: Option<usize>,
+        library: &Library,
+    ) -> Result<Self, Error> {
+        // SAFETY: the candidate is verified by its start time below.
+        let pid = unsafe { KWinPid::try_from(candidate.pid) }?;
+        let pidfd = match unsafe { libc::syscall(libc::SYS_pidfd_open, candidate.pid, 0) } {
+            -1 => match Error::last_errno() {
+                // kernel older than 5.3, fall back to the start time.
+                Error::Errno(libc::ENOSYS) => None,
+                e => return Err(e),
+            },
+            fd => Some(unsafe { std::os::fd::FromRawFd::from_raw_fd(fd as i32) }),
+        };
+        // the pid might be reused between scanning and `pidfd_open`, the pidfd keeps the identity afterwards.
+        if !same_start_time(candidate.pid, candidate.start_time) {
+            return Err(Error::Errno(libc::ESRCH));
+        }
+        let workspace = match offset {
+            Some(offset) => Workspace::try_get_with(pid, offset, library)?,
+            None => Workspace::try_get_cached(pid, library)?,
+        };
+        let mouse = workspace.try_get_mouse()?;
+        let ret = Self {
+            pidfd,
+            start_time: candidate.start_time,
+            workspace,
+            mouse,
+        };
+        // a zombie of the previous kwin_wayland might still be listed.
+        match ret.alive() {
+            true => Ok(ret),
+            false => Err(Error::Errno(libc::ESRCH)),
+        }
+    }
+    fn alive(&self) -> bool {
+        match &self.pidfd {
+            // a pidfd becomes readable once the process exits.
+            Some(fd) => {
+                let mut poll = libc::pollfd {
+                    fd: std::os::fd::AsRawFd::as_raw_fd(fd),
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                unsafe { libc::poll(&mut poll, 1, 0) == 0 }
+            }
+            None => same_start_time(self.workspace.pid().0, self.start_time),
+        }
+    }
+}
+fn same_start_time(pid: i32, start_time: u64) -> bool {
+    procfs::Candidate::read(Path::new("/proc"), pid)
+        .is_ok_and(|x| x.is_some_and(|x| x.start_time == start_time))
+}
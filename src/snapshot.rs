@@ -0,0 +1,192 @@
+// included in `pointer`.
+
+/// types that could be decoded from the bytes read by `Snapshot` (native endian, no padding).
+pub trait Decode: Sized {
+    /// bytes needed.
+    const SIZE: usize;
+    /// decode from exactly `SIZE` bytes.
+    fn decode(bytes: &[u8]) -> Self;
+}
+macro_rules! decode_num {
+    ($($t:ty)*) => {$(
+        impl Decode for $t {
+            const SIZE: usize = size_of::<$t>();
+            fn decode(bytes: &[u8]) -> Self {
+                let mut buf = [0; size_of::<$t>()];
+                buf.copy_from_slice(bytes);
+                <$t>::from_ne_bytes(buf)
+            }
+        }
+    )*};
+}
+decode_num!(u8 u16 u32 u64 usize i8 i16 i32 i64 isize f32 f64);
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    const SIZE: usize = A::SIZE + B::SIZE;
+    fn decode(bytes: &[u8]) -> Self {
+        (A::decode(&bytes[..A::SIZE]), B::decode(&bytes[A::SIZE..]))
+    }
+}
+impl<const N: usize> Decode for [u8; N] {
+    const SIZE: usize = N;
+    fn decode(bytes: &[u8]) -> Self {
+        let mut buf = [0; N];
+        buf.copy_from_slice(bytes);
+        buf
+    }
+}
+impl Decode for *mut c_void {
+    const SIZE: usize = size_of::<usize>();
+    fn decode(bytes: &[u8]) -> Self {
+        usize::decode(bytes) as *mut c_void
+    }
+}
+
+/// handle of a typed field registered in `Snapshot`.
+pub struct Field<T> {
+    index: usize,
+    _marker: std::marker::PhantomData<fn() -> T>,
+}
+impl<T> Clone for Field<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for Field<T> {}
+impl<T> Field<T> {
+    /// index of the field, which could be passed to `Reading::bytes`.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+struct Range {
+    remote: *mut c_void,
+    offset: usize,
+    len: usize,
+}
+
+/// read many remote ranges with one vectored `process_vm_readv`, so the values are sampled consistently.
+///
+/// ```no_run
+/// use kwin_mouse_loc::pointer::{Snapshot, Workspace};
+/// let workspace = unsafe { Workspace::new(true) };
+/// let mouse = workspace.get_mouse();
+/// let mut snapshot = Snapshot::new(workspace.pid());
+/// let pos = mouse.register(&mut snapshot);
+/// let object = snapshot.field::<*mut std::ffi::c_void>(workspace.addr());
+/// let reading = snapshot.read().unwrap();
+/// println!("{:?} {:?}", reading.get(pos), reading.get(object));
+/// ```
+///
+/// At most `libc::UIO_MAXIOV` (1024) ranges are read per syscall, more ranges need more syscalls.
+pub struct Snapshot {
+    pid: KWinPid,
+    ranges: Vec<Range>,
+    buf: Vec<u8>,
+}
+impl Snapshot {
+    /// an empty snapshot of `pid`.
+    pub fn new(pid: KWinPid) -> Self {
+        Self {
+            pid,
+            ranges: Vec::new(),
+            buf: Vec::new(),
+        }
+    }
+    /// register `len` bytes at `remote`, returns the index of the range.
+    pub fn register(&mut self, remote: *mut c_void, len: usize) -> usize {
+        self.ranges.push(Range {
+            remote,
+            offset: self.buf.len(),
+            len,
+        });
+        self.buf.resize(self.buf.len() + len, 0);
+        self.ranges.len() - 1
+    }
+    /// register a typed field at `remote`.
+    pub fn field<T: Decode>(&mut self, remote: *mut c_void) -> Field<T> {
+        Field {
+            index: self.register(remote, T::SIZE),
+            _marker: std::marker::PhantomData,
+        }
+    }
+    /// number of registered ranges.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+    /// whether no range is registered.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+    /// read all the registered ranges.
+    ///
+    /// The kernel never splits a remote range, once a range cannot be read, it and all the ranges after it
+    /// are reported as `Error::ShortRead` by `Reading`. Returns `Err` only if nothing could be read.
+    pub fn read(&mut self) -> Result<Reading<'_>, Error> {
+        let mut read = 0;
+        for chunk in self.ranges.chunks(libc::UIO_MAXIOV as usize) {
+            let start = chunk[0].offset;
+            let len = chunk.iter().map(|x| x.len).sum::<usize>();
+            let local = iovec {
+                iov_base: self.buf[start..].as_mut_ptr() as *mut c_void,
+                iov_len: len,
+            };
+            let remote = chunk
+                .iter()
+                .map(|x| iovec {
+                    iov_base: x.remote,
+                    iov_len: x.len,
+                })
+                .collect::<Vec<_>>();
+            // SAFETY: the local buffer holds exactly `len` bytes from `start`,
+            //         the remote side is checked by the kernel.
+            match unsafe {
+                process_vm_readv(self.pid.0, &local, 1, remote.as_ptr(), remote.len() as _, 0)
+            } {
+                -1 if read == 0 => return Err(Error::last_errno()),
+                -1 => break,
+                x => {
+                    read += x as usize;
+                    if x as usize != len {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(Reading {
+            snapshot: self,
+            read,
+        })
+    }
+}
+
+/// the result of `Snapshot::read`.
+pub struct Reading<'a> {
+    snapshot: &'a Snapshot,
+    read: usize,
+}
+impl Reading<'_> {
+    /// total bytes read.
+    pub fn read(&self) -> usize {
+        self.read
+    }
+    /// whether every range is read.
+    pub fn complete(&self) -> bool {
+        self.read == self.snapshot.buf.len()
+    }
+    /// bytes of the `index`-th range, or `Error::ShortRead` if it is not (fully) read.
+    pub fn bytes(&self, index: usize) -> Result<&[u8], Error> {
+        let range = &self.snapshot.ranges[index];
+        match self.read.saturating_sub(range.offset) {
+            x if x >= range.len => Ok(&self.snapshot.buf[range.offset..range.offset + range.len]),
+            x => Err(Error::ShortRead {
+                expected: range.len,
+                read: x,
+            }),
+        }
+    }
+    /// decode a typed field.
+    pub fn get<T: Decode>(&self, field: Field<T>) -> Result<T, Error> {
+        self.bytes(field.index).map(T::decode)
+    }
+}
@@ -0,0 +1,179 @@
+use crate::pointer::{Error, Mouse, Sample};
+use std::{
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+/// a `Stream`-like poller of the cursor position, driven by a `timerfd` registered in an `epoll` instance.
+///
+/// No runtime is needed: register `as_raw_fd()` (the epoll fd) for readability in any reactor, and call
+/// `poll_next` whenever it becomes readable. `poll_next` never blocks, it returns `Poll::Pending` until the timer
+/// fires and the position changes. The waker in `Context` is not stored, since the readiness is reported by the fd.
+///
+/// ```no_run
+/// use kwin_mouse_loc::{pointer::Workspace, stream::PositionStream};
+/// use std::time::Duration;
+/// let mouse = unsafe { Workspace::new(true) }.get_mouse();
+/// let mut stream = PositionStream::new(mouse, Duration::from_millis(10)).unwrap();
+/// // without a reactor, `next_blocking` waits on the epoll fd directly.
+/// while let Some(sample) = stream.next_blocking(Some(Duration::from_secs(5))) {
+///     println!("{:?}", sample.unwrap().pos);
+/// }
+/// ```
+pub struct PositionStream {
+    mouse: Mouse,
+    timer: OwnedFd,
+    epoll: OwnedFd,
+    last: Option<(Instant, (f64, f64))>,
+}
+fn check(ret: i32) -> Result<i32, Error> {
+    if ret == -1 {
+        Err(Error::last_errno())
+    } else {
+        Ok(ret)
+    }
+}
+impl PositionStream {
+    /// read `mouse` every `interval`.
+    ///
+    /// A zero `interval` would disarm the timer, it is raised to 1ns instead.
+    pub fn new(mouse: Mouse, interval: Duration) -> Result<Self, Error> {
+        let interval = interval.max(Duration::from_nanos(1));
+        // SAFETY: the fds are checked before being owned.
+        let timer = unsafe {
+            OwnedFd::from_raw_fd(check(libc::timerfd_create(
+                libc::CLOCK_MONOTONIC,
+                libc::TFD_NONBLOCK | libc::TFD_CLOEXEC,
+            ))?)
+        };
+        let epoll =
+            unsafe { OwnedFd::from_raw_fd(check(libc::epoll_create1(libc::EPOLL_CLOEXEC))?) };
+        let spec = libc::timespec {
+            tv_sec: interval.as_secs() as _,
+            tv_nsec: interval.subsec_nanos() as _,
+        };
+        let spec = libc::itimerspec {
+            it_interval: spec,
+            it_value: spec,
+        };
+        let mut event = libc::epoll_event {
+            events: libc::EPOLLIN as u32,
+            u64: timer.as_raw_fd() as u64,
+        };
+        unsafe {
+            check(libc::timerfd_settime(
+                timer.as_raw_fd(),
+                0,
+                &spec,
+                std::ptr::null_mut(),
+            ))?;
+            check(libc::epoll_ctl(
+                epoll.as_raw_fd(),
+                libc::EPOLL_CTL_ADD,
+                timer.as_raw_fd(),
+                &mut event,
+            ))?;
+        }
+        Ok(Self {
+            mouse,
+            timer,
+            epoll,
+            last: None,
+        })
+    }
+    /// the polled mouse.
+    pub fn mouse(&self) -> &Mouse {
+        &self.mouse
+    }
+    /// stop the timer and get the mouse back.
+    pub fn into_inner(self) -> Mouse {
+        self.mouse
+    }
+    /// consume the pending timer expirations, returns `false` if the timer has not fired yet.
+    fn expired(&self) -> Result<bool, Error> {
+        let mut count = 0u64;
+        match unsafe { libc::read(self.timer.as_raw_fd(), &mut count as *mut u64 as *mut _, 8) } {
+            8 => Ok(count > 0),
+            _ => match Error::last_errno() {
+                Error::Errno(libc::EAGAIN) => Ok(false),
+                e => Err(e),
+            },
+        }
+    }
+    /// `Stream::poll_next` without depending on `futures`, it never blocks.
+    ///
+    /// The first expiration reports the current position with zero delta, then only changes are reported.
+    pub fn poll_next(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Sample, Error>>> {
+        let this = self.get_mut();
+        loop {
+            match this.expired() {
+                Ok(true) => {}
+                Ok(false) => return Poll::Pending,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            }
+            let pos = match this.mouse.try_loc() {
+                Ok(x) => x,
+                Err(e) => return Poll::Ready(Some(Err(e))),
+            };
+            let time = Instant::now();
+            let (last_time, last_pos) = this.last.unwrap_or((time, pos));
+            if this.last.is_some() && pos == last_pos {
+                continue;
+            }
+            this.last = Some((time, pos));
+            let delta = (pos.0 - last_pos.0, pos.1 - last_pos.1);
+            let secs = time.duration_since(last_time).as_secs_f64();
+            return Poll::Ready(Some(Ok(Sample {
+                time,
+                pos,
+                delta,
+                velocity: if secs > 0.0 {
+                    (delta.0 / secs, delta.1 / secs)
+                } else {
+                    (0.0, 0.0)
+                },
+            })));
+        }
+    }
+    /// block on the epoll fd until the next change, `None` if `timeout` elapsed first.
+    pub fn next_blocking(&mut self, timeout: Option<Duration>) -> Option<Result<Sample, Error>> {
+        let deadline = timeout.map(|x| Instant::now() + x);
+        let waker = std::task::Waker::noop();
+        let mut cx = Context::from_waker(waker);
+        loop {
+            if let Poll::Ready(x) = Pin::new(&mut *self).poll_next(&mut cx) {
+                return x;
+            }
+            let ms = match deadline {
+                Some(d) => match d.checked_duration_since(Instant::now()) {
+                    Some(x) => x.as_millis().min(i32::MAX as u128) as i32,
+                    None => return None,
+                },
+                None => -1,
+            };
+            let mut event = libc::epoll_event { events: 0, u64: 0 };
+            if let Err(e) =
+                check(unsafe { libc::epoll_wait(self.epoll.as_raw_fd(), &mut event, 1, ms) })
+                && !matches!(e, Error::Errno(libc::EINTR))
+            {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+/// the epoll fd, which becomes readable when the timer fires.
+impl AsRawFd for PositionStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.epoll.as_raw_fd()
+    }
+}
+impl AsFd for PositionStream {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.epoll.as_fd()
+    }
+}
@@ -0,0 +1,385 @@
+// included in `consts`.
+
+/// the installed libkwin under `root`, as a path on the target system (i.e. without `root`).
+///
+/// The multiarch directories of Debian (`/usr/lib/<triplet>`) are searched first, then `/usr/lib64` (Fedora) and
+/// `/usr/lib` (Arch), preferring the versioned sonames since the unversioned link only comes with the dev package.
+pub fn find_libkwin(root: &Path) -> Option<std::path::PathBuf> {
+    let mut dirs = fs::read_dir(root.join("usr/lib"))
+        .into_iter()
+        .flatten()
+        .filter_map(|x| x.ok()?.file_name().into_string().ok())
+        .filter(|x| {
+            x.split_once('-').is_some_and(|(arch, os)| {
+                arch.starts_with(env::consts::ARCH) && os.contains("linux")
+            })
+        })
+        .map(|x| format!("/usr/lib/{x}"))
+        .collect::<Vec<_>>();
+    dirs.sort();
+    dirs.extend(["/usr/lib64".to_owned(), "/usr/lib".to_owned()]);
+    ["libkwin.so.6", "libkwin.so.5", "libkwin.so"]
+        .into_iter()
+        .flat_map(|name| dirs.iter().map(move |dir| format!("{dir}/{name}")))
+        .find(|x| root.join(&x[1..]).exists())
+        .map(Into::into)
+}
+
+/// `find_libkwin` on the running system.
+fn installed_libkwin() -> Option<String> {
+    find_libkwin(Path::new("/"))?
+        .into_os_string()
+        .into_string()
+        .ok()
+}
+
+/// options of `try_update_offset`.
+pub struct UpdateOptions {
+    pos: Option<String>,
+    kwin: Option<String>,
+    storage: Storage,
+    dry_run: bool,
+    verify: bool,
+    history: Option<std::path::PathBuf>,
+}
+impl Default for UpdateOptions {
+    fn default() -> Self {
+        Self {
+            pos: None,
+            kwin: Some(installed_libkwin().unwrap_or_else(|| "/usr/lib/libkwin.so".to_owned())),
+            storage: Storage::Executable,
+            dry_run: false,
+            verify: false,
+            history: Some(crate::offsets::history_path()),
+        }
+    }
+}
+impl UpdateOptions {
+    /// read the libkwin found by `find_libkwin`, or `/usr/lib/libkwin.so`, save into the executable, record into `offsets::history_path()`, neither dry run nor verify.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// the include paths (one per line) to compute `POS_OFFSET` with bindgen, requires `update-pos`.
+    ///
+    /// If `None`, `POS_OFFSET` is read from the debug info of libkwin when available.
+    pub fn pos(mut self, pos: Option<&str>) -> Self {
+        self.pos = pos.map(ToOwned::to_owned);
+        self
+    }
+    /// path of libkwin to read `WORKSPACE_OFFSET` from, `None` keeps it.
+    pub fn kwin(mut self, kwin: Option<&str>) -> Self {
+        self.kwin = kwin.map(ToOwned::to_owned);
+        self
+    }
+    /// where to save the new offsets.
+    pub fn storage(mut self, storage: Storage) -> Self {
+        self.storage = storage;
+        self
+    }
+    /// compute the new offsets without saving them or changing the statics.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+    /// check the new offsets with `Workspace::validate` on the running kwin_wayland before saving them.
+    ///
+    /// The running kwin_wayland should have loaded the same libkwin, i.e., restarted after an upgrade.
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+    /// the history file to record the update into, see `list_offset_history`. `None` records nothing.
+    pub fn history(mut self, history: Option<&Path>) -> Self {
+        self.history = history.map(ToOwned::to_owned);
+        self
+    }
+}
+
+/// errors of `try_update_offset`.
+#[derive(Debug)]
+pub enum UpdateError {
+    /// libkwin cannot be parsed, or `KWin::Workspace::_self` is missing.
+    Elf(crate::elf::Error),
+    /// include paths are given, but the feature `update-pos` is disabled.
+    NeedsBindgen,
+    /// bindgen failed to compute `POS_OFFSET` from the include paths, see `try_offset_pos`.
+    Bindgen(String),
+    /// the running kwin_wayland cannot be attached for verification.
+    Attach(crate::pointer::Error),
+    /// the new offsets are not trusted by `Workspace::validate`.
+    Rejected(Box<crate::pointer::Validation>),
+    /// the cache file cannot be updated.
+    Cache(crate::offsets::Error),
+    /// no libkwin is given nor found by `find_libkwin`, thus the cache entry cannot be keyed.
+    NoLibkwin,
+    /// `Offset::Offset` is given to the cache file, the section offsets only exist in the executable.
+    NotCacheable,
+    /// the executable cannot be patched.
+    Save(SaveError),
+    /// the history file cannot be read or written. The offsets are already saved if it happens when recording.
+    History(crate::offsets::Error),
+    /// there are not so many updates in the history to roll back.
+    NoHistory(usize),
+}
+impl std::fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Elf(e) => write!(f, "{e}"),
+            Self::NeedsBindgen => write!(
+                f,
+                "update-pos relies on bindgen and source code of kwin, which is not enabled"
+            ),
+            Self::Bindgen(e) => write!(f, "cannot compute POS_OFFSET with bindgen: {e}"),
+            Self::Attach(e) => write!(f, "cannot verify with kwin_wayland: {e}"),
+            Self::Rejected(x) => write!(f, "new offsets are rejected ({x})"),
+            Self::Cache(e) => write!(f, "{e}"),
+            Self::NoLibkwin => write!(
+                f,
+                "libkwin is not found, the cached offsets cannot be keyed"
+            ),
+            Self::NotCacheable => write!(f, "the section offsets only exist in the executable"),
+            Self::Save(e) => write!(f, "{e}"),
+            Self::History(e) => write!(f, "offset history: {e}"),
+            Self::NoHistory(n) => write!(f, "no update #{n} in the offset history"),
+        }
+    }
+}
+impl std::error::Error for UpdateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Elf(e) => Some(e),
+            Self::Attach(e) => Some(e),
+            Self::Cache(e) => Some(e),
+            Self::Save(e) => Some(e),
+            Self::History(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// report of `try_update_offset`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OffsetUpdate {
+    /// `WORKSPACE_OFFSET` before the update.
+    pub old_workspace: usize,
+    /// `POS_OFFSET` before the update.
+    pub old_pos: usize,
+    /// the new `WORKSPACE_OFFSET`, `None` if not computed.
+    pub new_workspace: Option<usize>,
+    /// the new `POS_OFFSET`, `None` if not computed.
+    pub new_pos: Option<usize>,
+    /// why `new_pos` is not computed, e.g., the debug info is not installed.
+    pub pos_skipped: Option<String>,
+    /// the result of verification, if requested.
+    pub validation: Option<crate::pointer::Validation>,
+    /// whether the new offsets are saved (false for dry runs).
+    pub written: bool,
+}
+impl OffsetUpdate {
+    /// `WORKSPACE_OFFSET` after the update.
+    pub fn workspace(&self) -> usize {
+        self.new_workspace.unwrap_or(self.old_workspace)
+    }
+    /// `POS_OFFSET` after the update.
+    pub fn pos(&self) -> usize {
+        self.new_pos.unwrap_or(self.old_pos)
+    }
+    /// whether any offset differs from the old one.
+    pub fn changed(&self) -> bool {
+        (self.workspace(), self.pos()) != (self.old_workspace, self.old_pos)
+    }
+}
+impl std::fmt::Display for OffsetUpdate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Before update, WORKSPACE_OFFSET = 0x{:06x}, POS_OFFSET = 0x{:04x}.",
+            self.old_workspace, self.old_pos
+        )?;
+        if let Some(x) = &self.pos_skipped {
+            writeln!(f, "POS_OFFSET is not updated: {x}")?;
+        }
+        if let Some(x) = &self.validation {
+            writeln!(f, "Verified: {x}")?;
+        }
+        write!(
+            f,
+            "{}, WORKSPACE_OFFSET = 0x{:06x}, POS_OFFSET = 0x{:04x}.",
+            if self.written {
+                "After update"
+            } else {
+                "Dry run"
+            },
+            self.workspace(),
+            self.pos()
+        )
+    }
+}
+
+/// update `WORKSPACE_OFFSET` and `POS_OFFSET` without terminating the execution flow.
+///
+/// The new offsets are computed, verified if requested, then saved into the storage and the statics.
+/// Nothing is saved for dry runs, or if any step fails.
+/// ```no_run
+/// #[cfg(feature = "update-offset")]
+/// {
+///     use kwin_mouse_loc::consts::{try_update_offset, UpdateOptions};
+///     let report = try_update_offset(&UpdateOptions::new().dry_run(true).verify(true)).unwrap();
+///     println!("{report}");
+/// }
+/// ```
+pub fn try_update_offset(options: &UpdateOptions) -> Result<OffsetUpdate, UpdateError> {
+    // SAFETY: the statics are only written below, as `update_offset` always did.
+    let (old_workspace, old_pos) = unsafe { (WORKSPACE_OFFSET, POS_OFFSET) };
+    let mut pos_skipped = None;
+    let new_pos = match (&options.pos, &options.kwin) {
+        #[cfg(feature = "update-pos")]
+        (Some(pos), _) => Some(try_offset_pos(pos).map_err(UpdateError::Bindgen)?),
+        #[cfg(not(feature = "update-pos"))]
+        (Some(_), _) => return Err(UpdateError::NeedsBindgen),
+        // without headers, the debug info (if installed) still tells the field offset.
+        (None, Some(kwin)) => offset_pos_from_dwarf(kwin)
+            .map_err(|e| pos_skipped = Some(e.to_string()))
+            .ok(),
+        (None, None) => None,
+    };
+    let new_workspace = match &options.kwin {
+        Some(kwin) => Some(crate::elf::workspace_offset(kwin).map_err(UpdateError::Elf)?),
+        None => None,
+    };
+    let mut report = OffsetUpdate {
+        old_workspace,
+        old_pos,
+        new_workspace,
+        new_pos,
+        pos_skipped,
+        validation: None,
+        written: false,
+    };
+    if options.verify {
+        use crate::pointer::{KWinPid, Workspace};
+        let pid = unsafe { KWinPid::try_search(true) }.map_err(UpdateError::Attach)?;
+        let workspace = Workspace::try_get(pid, report.workspace())
+            .map_err(UpdateError::Attach)?
+            .with_pos_offset(report.pos());
+        let validation = workspace.validate(&[]);
+        if !validation.trusted() {
+            return Err(UpdateError::Rejected(Box::new(validation)));
+        }
+        report.validation = Some(validation);
+    }
+    if options.dry_run {
+        return Ok(report);
+    }
+    // the libkwin the offsets are recorded (and cached) for.
+    let kwin = match (&options.kwin, &options.storage) {
+        (Some(x), _) => x.clone(),
+        (None, Storage::Cache(_)) => installed_libkwin().ok_or(UpdateError::NoLibkwin)?,
+        (None, Storage::Executable) => {
+            installed_libkwin().unwrap_or_else(|| "/usr/lib/libkwin.so".to_owned())
+        }
+    };
+    let entry = save_update(&options.storage, &kwin, &report)?;
+    unsafe {
+        WORKSPACE_OFFSET = report.workspace();
+        POS_OFFSET = report.pos();
+    }
+    report.written = true;
+    if let Some(history) = options.history.as_deref().filter(|_| entry.changed()) {
+        record_offset_history(history, &entry)?;
+    }
+    Ok(report)
+}
+
+/// save the new offsets of `report` read from `kwin` into `storage`, without touching the statics.
+///
+/// Returns the history entry of the update, whose old offsets are the cached ones for the cache.
+pub(crate) fn save_update(
+    storage: &Storage,
+    kwin: &str,
+    report: &OffsetUpdate,
+) -> Result<HistoryEntry, UpdateError> {
+    let entry = HistoryEntry::new(kwin, storage.clone(), report);
+    let updates = [
+        (report.new_pos, Offset::Pos),
+        (report.new_workspace, Offset::Kwin),
+    ];
+    match storage {
+        Storage::Executable => {
+            for (val, item) in updates {
+                let Some(val) = val else { continue };
+                unsafe { try_save_offset(val, item) }.map_err(UpdateError::Save)?;
+            }
+            Ok(entry)
+        }
+        Storage::Cache(path) => {
+            let key = crate::offsets::Key::of_library(kwin).map_err(UpdateError::Elf)?;
+            let (old_workspace, old_pos) = cached_offsets(path, &key)?;
+            for (val, item) in updates {
+                let Some(val) = val else { continue };
+                save_offset_to_key(path, key.clone(), val, item)?;
+            }
+            Ok(HistoryEntry {
+                old_workspace,
+                old_pos,
+                new_workspace: report.new_workspace.or(old_workspace),
+                new_pos: report.new_pos.or(old_pos),
+                ..entry
+            })
+        }
+    }
+}
+
+/// fallible version of `save_offset_to_cache`.
+pub fn try_save_offset_to_cache(
+    path: &Path,
+    kwin: &str,
+    val: usize,
+    item: Offset,
+) -> Result<(), UpdateError> {
+    let key = crate::offsets::Key::of_library(kwin).map_err(UpdateError::Elf)?;
+    save_offset_to_key(path, key, val, item)
+}
+fn save_offset_to_key(
+    path: &Path,
+    key: crate::offsets::Key,
+    val: usize,
+    item: Offset,
+) -> Result<(), UpdateError> {
+    use crate::offsets::Database;
+    let mut db = Database::load_or_empty(path).map_err(UpdateError::Cache)?;
+    let mut entry = db.get(&key).cloned().unwrap_or_default();
+    match item {
+        Offset::Offset => return Err(UpdateError::NotCacheable),
+        Offset::Pos => entry.pos = Some(val),
+        Offset::Kwin => entry.workspace = Some(val),
+    }
+    db.insert(key, entry);
+    db.save(path).map_err(UpdateError::Cache)
+}
+/// the (workspace, pos) offsets cached for `key` in `path`.
+fn cached_offsets(
+    path: &Path,
+    key: &crate::offsets::Key,
+) -> Result<(Option<usize>, Option<usize>), UpdateError> {
+    let db = crate::offsets::Database::load_or_empty(path).map_err(UpdateError::Cache)?;
+    Ok(db.get(key).map_or((None, None), |x| (x.workspace, x.pos)))
+}
+/// replace the offsets cached for `key` in `path`, `None` removes an offset, and the entry once it is empty.
+fn restore_cached_offsets(
+    path: &Path,
+    key: crate::offsets::Key,
+    workspace: Option<usize>,
+    pos: Option<usize>,
+) -> Result<(), UpdateError> {
+    use crate::offsets::{Database, Entry};
+    let mut db = Database::load_or_empty(path).map_err(UpdateError::Cache)?;
+    let mut entry = db.get(&key).cloned().unwrap_or_default();
+    (entry.workspace, entry.pos) = (workspace, pos);
+    if entry == Entry::default() {
+        db.remove(&key);
+    } else {
+        db.insert(key, entry);
+    }
+    db.save(path).map_err(UpdateError::Cache)
+}
@@ -1,5 +1,5 @@
-use std::{env, path::Path, fs::{self, OpenOptions}, process::Command};
-/// Update offset for WORKSPACE_OFFSET and POS_OFFSET using default path.
+use std::{env, path::Path, fs::{self, OpenOptions}};
+/// Update offset for WORKSPACE_OFFSET and POS_OFFSET using default path, i.e., the libkwin found by `find_libkwin`.
 ///
 /// Since it modify static variable and executable, this function terminates the execution flow.
 /// ```no_run
@@ -8,22 +8,27 @@ use std::{env, path::Path, fs::{self, OpenOptions}, process::Command};
 /// #[cfg(feature = "update-offset")]
 /// kwin_mouse_loc::consts::update_offset()
 /// ```
-/// Such function might be compined with some post-update hooks (e.g., with pacman's hook defined in, for example, `/etc/pacman.d/hooks/kwin.hook`)
+/// Such function might be compined with some post-update hooks (e.g., with pacman's hook defined in, for example, `/etc/pacman.d/hooks/kwin.hook`),
+/// which could be generated by `Hook` or the `install-hooks` command of `subcommand`.
 /// ```text
 /// [Trigger]
 /// Operation = Install
 /// Operation = Upgrade
-/// Type = Package
-/// Target = kwin
+/// Type = Path
+/// Target = usr/lib/libkwin.so*
 /// [Action]
 /// Description = "Update outdated clicker."
 /// When = PostTransaction
 /// Exec = /bin/clicker update-offset
 /// ```
+/// Each update is recorded into `offsets::history_path()`, wrong offsets could be restored by `rollback_offset`.
 pub fn update_offset() -> ! {
-    #[cfg(feature = "update-pos")]
-    let pos = Some(
-        r#"
+    update_offset_custom(DEFAULT_POS, UpdateOptions::default().kwin.as_deref());
+}
+/// include paths used by `update_offset` to compute POS_OFFSET, `None` without `update-pos`.
+#[cfg(feature = "update-pos")]
+pub const DEFAULT_POS: Option<&str> = Some(
+    r#"
                         /usr/include
                         /usr/include/kwin
                         /usr/include/KF6/KConfig
@@ -35,43 +40,37 @@ pub fn update_offset() -> ! {
                         /usr/include/qt6/QtGui
                         /usr/include/qt6/QtWidgets
                     "#,
-    );
-    #[cfg(not(feature = "update-pos"))]
-    let pos = None;
-    let kwin = Some("/usr/lib/libkwin.so");
-    update_offset_custom(pos, kwin);
-}
+);
+/// include paths used by `update_offset` to compute POS_OFFSET, `None` without `update-pos`.
+#[cfg(not(feature = "update-pos"))]
+pub const DEFAULT_POS: Option<&str> = None;
 
 /// Update offset for WORKSPACE_OFFSET and POS_OFFSET using provided path.
 ///
+/// If `pos` is `None`, POS_OFFSET is read from the debug info of `kwin` (see `offset_pos_from_dwarf`) when available.
+///
 /// Since it modify static variable and executable, this function terminates the execution flow.
 pub fn update_offset_custom(pos: Option<&str>, kwin: Option<&str>) -> ! {
-    unsafe {
-        let wo = WORKSPACE_OFFSET;
-        let po = POS_OFFSET;
-        println!("Before update, WORKSPACE_OFFSET = 0x{wo:06x}, POS_OFFSET = 0x{po:04x}.");
-    }
-    unsafe {
-        if let Some(pos) = pos {
-            #[cfg(feature = "update-pos")]
-            {
-                POS_OFFSET = offset_pos(pos);
-                save_offset(POS_OFFSET, Offset::Pos);
-            }
-            #[cfg(not(feature = "update-pos"))]
-            {
-                panic!("update-pos relies on bindgen and source code of kwin, which is not enabled by default, the provided {pos} cannot be dealt without bindgen, and thus the update failed.");
-            }
-        }
-        if let Some(kwin) = kwin {
-            WORKSPACE_OFFSET = offset_kwin(kwin);
-            save_offset(WORKSPACE_OFFSET, Offset::Kwin);
-        }
-    }
-    unsafe {
-        let wo = WORKSPACE_OFFSET;
-        let po = POS_OFFSET;
-        println!("After update, WORKSPACE_OFFSET = 0x{wo:06x}, POS_OFFSET = 0x{po:04x}.");
+    update_offset_into(pos, kwin, Storage::Executable)
+}
+/// where `update_offset_into` saves the offsets.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Storage {
+    /// patch the `.kwin.mouse.loc.*` sections of the current executable, see `save_offset`.
+    Executable,
+    /// the cache file at this path (e.g., `crate::offsets::cache_path()`), keyed by the build-id of libkwin.
+    ///
+    /// Neither readelf nor write access to the executable is needed, and `Workspace::new` loads it from `cache_path()`.
+    Cache(std::path::PathBuf),
+}
+/// like `update_offset_custom`, but the offsets are saved into `storage`.
+///
+/// Since it modify static variable, this function terminates the execution flow, use `try_update_offset` to continue.
+pub fn update_offset_into(pos: Option<&str>, kwin: Option<&str>, storage: Storage) -> ! {
+    let options = UpdateOptions::new().pos(pos).kwin(kwin).storage(storage);
+    match try_update_offset(&options) {
+        Ok(report) => println!("{report}"),
+        Err(e) => panic!("update failed: {e}"),
     }
     std::panic::set_hook(Box::new(|_|{}));
     panic!();
@@ -82,10 +81,12 @@ pub enum Offset {
     Pos,
     Kwin,
 }
+#[used]
 #[unsafe(link_section = ".kwin.mouse.loc.offset")]
 static mut OFFSET: [usize; 3] = [0, 0xfacefeedcafebabe, 0xdeadbeeffee1dead];
 
 /// get section's offset from the output of `readelf -WCS`.
+#[deprecated = "sections are located by `patch_offset` without readelf"]
 pub fn get_offset(data: &str, section: &str) -> usize {
     usize::from_str_radix(
         data.split_once(section)
@@ -102,134 +103,35 @@ pub fn get_offset(data: &str, section: &str) -> usize {
     .expect("cannot parse address as usize")
 }
 
-/// Save offset into the program itself. Also modify static mut variables.
+/// Save offset into the program itself, the sections are located by the ELF section headers of the executable.
 ///
 /// Do not use it unless you know what you're doing.
 pub unsafe fn save_offset(val: usize, item: Offset) {
-    use std::io::{Read, Seek, SeekFrom, Write};
-
-    let exe = &env::current_exe().expect("cannot find current exe");
-    #[derive(Default)]
-    enum DropGuard<T: AsRef<Path>, U: AsRef<Path>> {
-        Some(T, U),
-        #[default]
-        None
-    }
-    impl <T: AsRef<Path>, U: AsRef<Path>> DropGuard<T,U> {
-        pub fn new(exe:T, tmp:U) -> Self {
-            println!("(DropGuard: Using drop guard to prevent ETXTBSY issues.)");
-            Self::Some(exe, tmp)
-        }
+    match unsafe { try_save_offset(val, item) } {
+        Ok(Patched::InPlace) => {}
+        Ok(Patched::Copy) => println!("(The executable is busy, a patched copy is renamed over it.)"),
+        Err(e) => panic!("cannot save offset: {e}"),
     }
-    impl<T: AsRef<Path>, U: AsRef<Path>> Drop for DropGuard<T,U> {
-        fn drop(&mut self) {
-            if let Self::Some(tmp, exe) = self {
-                fs::remove_file(&exe).expect("Execution failed, the original file cannot be deleted.");
-                fs::copy(tmp, exe).expect("Execution failed, the original place cannot be written.");
-                println!("(DropGuard: Done.)");
-            }
-        }
-    }
-    let (mut file, drop_guard) = if let Ok(file) = OpenOptions::new().read(true).write(true).open(exe) {
-        (file, Default::default())
-    } else {
-        use std::time::{Duration, SystemTime, UNIX_EPOCH};
-        let mut x = std::env::temp_dir();
-        x.push(format!("kwin-mouse-loc.{}.tmp", SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::from_nanos(u64::MAX)).as_nanos()));
-        fs::copy(exe, &x).expect("cannot visit tmp file.");
-        let file = OpenOptions::new().read(true).write(true).open(&x).expect("can neither current exe nor the tmp file can be modified.");
-        (file, DropGuard::new(x, exe))
-    };
-    let offset = unsafe {
-        if OFFSET[0] == 0 {
-            println!(
-                "Before offset init, offset is 0x{:016x} 0x{:016x} 0x{:016x}.",
-                OFFSET[0], OFFSET[1], OFFSET[2]
-            );
-            let data = &String::from_utf8(
-                Command::new("readelf")
-                    .arg("-WCS")
-                    .arg(exe)
-                    .output()
-                    .expect("readelf execute failed")
-                    .stdout,
-            )
-            .expect("failed to parse readelf");
-            let mut new = [0; 3];
-            new[0] = get_offset(data, ".kwin.mouse.loc.offset");
-            new[1] = get_offset(data, ".kwin.mouse.loc.pos");
-            new[2] = get_offset(data, ".kwin.mouse.loc.kwin");
-            file.seek(SeekFrom::Start(new[0] as u64))
-                .expect("cannot seek file");
-            let mut tmp = [0u8; 24];
-            file.read(&mut tmp).expect("cannot read offset");
-            assert!(
-                tmp[0..8] == OFFSET[0].to_ne_bytes()
-                    && tmp[8..16] == OFFSET[1].to_ne_bytes()
-                    && tmp[16..24] == OFFSET[2].to_ne_bytes(),
-                "value mismatch! The offset might be incorrect.",
-            );
-            file.seek(SeekFrom::Start(new[0] as u64))
-                .expect("cannot seek file");
-            file.write(&new[0].to_ne_bytes())
-                .expect("cannot save file.");
-            file.write(&new[1].to_ne_bytes())
-                .expect("cannot save file.");
-            file.write(&new[2].to_ne_bytes())
-                .expect("cannot save file.");
-            OFFSET = new;
-            println!(
-                "After offset init, offset is 0x{:06x} 0x{:06x} 0x{:06x}, which should not be changed in further executions.",
-                OFFSET[0], OFFSET[1], OFFSET[2]
-            );
-            //            readelf -WCS
-        }
-        match item {
-            Offset::Offset => OFFSET[0],
-            Offset::Pos => OFFSET[1],
-            Offset::Kwin => OFFSET[2],
-        }
-    };
-    file.seek(SeekFrom::Start(offset as u64))
-        .expect("cannot seek file");
-    file.write(&val.to_ne_bytes()).expect("cannot save file.");
-    file.sync_all().expect("cannot sync file.");
-    drop(drop_guard)
+}
+/// Save offset of the libkwin located at `kwin` into the cache file at `path`, which is replaced atomically.
+pub fn save_offset_to_cache(path: &Path, kwin: &str, val: usize, item: Offset) {
+    try_save_offset_to_cache(path, kwin, val, item).unwrap_or_else(|e| panic!("cannot save {}: {e}", path.display()))
 }
 /// got offset of `KWin::Workspace::_self` from elf file
 pub fn offset_kwin(elf_file: &str) -> usize {
-    usize::from_str_radix(
-        String::from_utf8(
-            Command::new("readelf")
-                .args(["-WCs", elf_file])
-                .output()
-                .expect("readelf execute failed")
-                .stdout,
-        )
-        .expect("failed to parse readelf")
-        .split_once(r#"KWin::Workspace::_self"#)
-        .expect("cannot find KWin::Workspace::_self")
-        .0
-        .rsplit_once('\n')
-        .expect("cannot read offset of KWin::Workspace::_self")
-        .1
-        .split_once(':')
-        .expect("parse `:` failed.")
-        .1
-        .trim()
-        .split_once(' ')
-        .expect("cannot parse space")
-        .0,
-        16,
-    )
-    .expect("cannot be parsed as hex digits")
+    crate::elf::workspace_offset(elf_file).unwrap_or_else(|e| panic!("cannot read offset of KWin::Workspace::_self: {e}"))
 }
 #[cfg(any(doc,feature = "update-pos"))]
 #[cfg_attr(doc, doc(cfg(feature = "update-pos")))]
 /// got the offset of `focusMousePos` in KWin_Workspace from the source code of KWin.
 pub fn offset_pos(s: &str) -> usize {
-    usize::from_str_radix(
-        bindgen::Builder::default()
+    try_offset_pos(s).unwrap_or_else(|e| panic!("{e}"))
+}
+#[cfg(any(doc,feature = "update-pos"))]
+#[cfg_attr(doc, doc(cfg(feature = "update-pos")))]
+/// fallible version of `offset_pos`.
+pub fn try_offset_pos(s: &str) -> Result<usize, String> {
+    let bindings = bindgen::Builder::default()
         // The input header we would like to generate
         // bindings for.
         .use_core()
@@ -248,19 +150,18 @@ pub fn offset_pos(s: &str) -> usize {
         .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
         // Finish the builder and generate the bindings.
         .generate()
-        // Unwrap the Result and panic on failure.
-        .expect("Unable to generate bindings")
-            .to_string()
-            .split_once(r#"offset_of!(KWin_Workspace, focusMousePos)"#)
-            .expect("cannot calculate offset of focusMousePos.")
-            .1
-            .split_once("]")
-            .expect(r#"do not find line [::core::mem::offset_of!(KWin_Workspace, focusMousePos) - $(SIZE)]."#)
-            .0
-            .split_once("-")
-            .expect("grab offset failed")
-            .1
-            .trim(),16
-    )
-    .expect("cannot be parsed as hex digits")
+        .map_err(|e| format!("Unable to generate bindings: {e}"))?
+        .to_string();
+    let offset = bindings
+        .split_once(r#"offset_of!(KWin_Workspace, focusMousePos)"#)
+        .ok_or("cannot calculate offset of focusMousePos.")?
+        .1
+        .split_once("]")
+        .ok_or(r#"do not find line [::core::mem::offset_of!(KWin_Workspace, focusMousePos) - $(SIZE)]."#)?
+        .0
+        .split_once("-")
+        .ok_or("grab offset failed")?
+        .1
+        .trim();
+    usize::from_str_radix(offset, 16).map_err(|e| format!("{offset:?} cannot be parsed as hex digits: {e}"))
 }
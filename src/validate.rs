@@ -0,0 +1,248 @@
+// included in `pointer`.
+
+/// the outcome of a single check of `Validation`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Check {
+    /// the check passed.
+    Passed,
+    /// the check failed, with the reason.
+    Failed(String),
+    /// the check cannot be done, with the reason.
+    Skipped(String),
+}
+impl Check {
+    fn weighted(&self, weight: f64) -> f64 {
+        match self {
+            Self::Passed => weight,
+            Self::Failed(_) => 0.0,
+            // neither trusted nor distrusted.
+            Self::Skipped(_) => weight / 2.0,
+        }
+    }
+}
+impl Display for Check {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Passed => write!(f, "passed"),
+            Self::Failed(x) => write!(f, "failed: {x}"),
+            Self::Skipped(x) => write!(f, "skipped: {x}"),
+        }
+    }
+}
+
+/// geometry of an output in the global (logical) coordinates of kwin, e.g., from `kscreen-doctor -o`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    /// left edge.
+    pub x: f64,
+    /// top edge.
+    pub y: f64,
+    /// width in logical pixels.
+    pub width: f64,
+    /// height in logical pixels.
+    pub height: f64,
+}
+impl Rect {
+    /// a rect from its top-left corner and size.
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+    /// whether `pos` is inside the rect, the right and bottom edges are excluded.
+    pub fn contains(&self, pos: (f64, f64)) -> bool {
+        (self.x..self.x + self.width).contains(&pos.0)
+            && (self.y..self.y + self.height).contains(&pos.1)
+    }
+    /// the smallest rect containing all `rects`, `None` if there is none.
+    pub fn bounding(rects: &[Self]) -> Option<Self> {
+        let (first, rest) = rects.split_first()?;
+        let (mut left, mut top) = (first.x, first.y);
+        let (mut right, mut bottom) = (first.x + first.width, first.y + first.height);
+        for x in rest {
+            left = left.min(x.x);
+            top = top.min(x.y);
+            right = right.max(x.x + x.width);
+            bottom = bottom.max(x.y + x.height);
+        }
+        Some(Self::new(left, top, right - left, bottom - top))
+    }
+}
+
+/// confidence report returned by `Workspace::validate`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Validation {
+    /// the remote address of the `KWin::Workspace` object, if `_self` could be read.
+    pub object: Option<*mut c_void>,
+    /// the position read before moving the cursor, if it could be read.
+    pub pos: Option<(f64, f64)>,
+    /// the object lies in a heap mapping of `/proc/{pid}/maps`.
+    pub heap: Check,
+    /// both coordinates are finite.
+    pub finite: Check,
+    /// the position is inside the union of the given outputs.
+    pub outputs: Check,
+    /// moving the cursor through uinput changes the position in the expected direction.
+    pub movement: Check,
+}
+impl Validation {
+    /// every check with its name.
+    pub fn checks(&self) -> [(&'static str, &Check); 4] {
+        [
+            ("heap", &self.heap),
+            ("finite", &self.finite),
+            ("outputs", &self.outputs),
+            ("movement", &self.movement),
+        ]
+    }
+    /// a score in `0.0..=1.0`, skipped checks count for half of their weights.
+    ///
+    /// Non-finite coordinates are conclusive, the score is 0 in that case.
+    pub fn confidence(&self) -> f64 {
+        if let Check::Failed(_) = self.finite {
+            return 0.0;
+        }
+        self.heap.weighted(0.3)
+            + self.finite.weighted(0.2)
+            + self.outputs.weighted(0.2)
+            + self.movement.weighted(0.3)
+    }
+    /// no check failed, and the confidence is at least one half.
+    pub fn trusted(&self) -> bool {
+        self.checks()
+            .iter()
+            .all(|x| !matches!(x.1, Check::Failed(_)))
+            && self.confidence() >= 0.5
+    }
+}
+impl Display for Validation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "confidence {:.2}", self.confidence())?;
+        for (name, check) in self.checks() {
+            write!(f, ", {name} {check}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Workspace {
+    /// check whether the workspace (and its mouse) looks sane, without moving the cursor.
+    ///
+    /// A stale `WORKSPACE_OFFSET` or `POS_OFFSET` usually gives a pointer outside of the heap, or nonsense
+    /// coordinates. Pass the output geometries as `outputs`, an empty slice skips that check.
+    pub fn validate(&self, outputs: &[Rect]) -> Validation {
+        let mut ret = self.validate_static(outputs);
+        ret.movement = Check::Skipped("use `validate_with_device` to move the cursor".into());
+        ret
+    }
+    /// like `validate`, and also moves the real cursor by `step` pixels with `device` and moves it back.
+    ///
+    /// Pointer acceleration makes the distance unpredictable, thus only the direction is checked.
+    #[cfg(feature = "uinput")]
+    pub fn validate_with_device(
+        &self,
+        outputs: &[Rect],
+        device: &mut crate::device::IoCtl,
+        step: i32,
+    ) -> Validation {
+        let mut ret = self.validate_static(outputs);
+        let (Some(p0), Check::Passed) = (ret.pos, &ret.finite) else {
+            ret.movement = Check::Skipped("no valid position to start with".into());
+            return ret;
+        };
+        let mouse = match self.try_get_mouse() {
+            Ok(x) => x,
+            Err(e) => {
+                ret.movement = Check::Failed(e.to_string());
+                return ret;
+            }
+        };
+        let settle = std::time::Duration::from_millis(50);
+        let mut moved = |dx, dy| {
+            device.move_mouse(dx, dy);
+            std::thread::sleep(settle);
+            mouse.try_loc()
+        };
+        ret.movement = match (moved(step, step), moved(-step, -step)) {
+            (Ok(p1), Ok(p2)) => {
+                let forward = (p1.0 - p0.0, p1.1 - p0.1);
+                let backward = (p2.0 - p1.0, p2.1 - p1.1);
+                let sign = step.signum() as f64;
+                // the cursor might be clamped at the edges, so one of the moves is enough.
+                if forward.0 * sign < 0.0
+                    || forward.1 * sign < 0.0
+                    || backward.0 * sign > 0.0
+                    || backward.1 * sign > 0.0
+                {
+                    Check::Failed(format!(
+                        "moved in the wrong direction: {forward:?} then {backward:?}"
+                    ))
+                } else if forward == (0.0, 0.0) && backward == (0.0, 0.0) {
+                    Check::Failed("the position does not follow the device".into())
+                } else {
+                    Check::Passed
+                }
+            }
+            (Err(e), _) | (_, Err(e)) => Check::Failed(e.to_string()),
+        };
+        ret
+    }
+    fn validate_static(&self, outputs: &[Rect]) -> Validation {
+        let unreadable = |e: &Error| Check::Skipped(format!("workspace is unreadable: {e}"));
+        let object = match self.try_object() {
+            Ok(x) => x,
+            Err(e) => {
+                return Validation {
+                    object: None,
+                    pos: None,
+                    heap: Check::Failed(e.to_string()),
+                    finite: unreadable(&e),
+                    outputs: unreadable(&e),
+                    movement: unreadable(&e),
+                };
+            }
+        };
+        let heap = match procfs::read_maps(Path::new("/proc"), self.0.0) {
+            Ok(maps) => match maps
+                .iter()
+                .find(|x| (x.start..x.end).contains(&(object as usize)))
+            {
+                Some(x) if x.heap() => Check::Passed,
+                Some(x) => Check::Failed(format!(
+                    "{object:?} is in {} {}",
+                    x.perms,
+                    x.path.as_deref().unwrap_or("[anon]")
+                )),
+                None => Check::Failed(format!("{object:?} is not mapped")),
+            },
+            Err(e) => Check::Skipped(format!("cannot read maps: {e}")),
+        };
+        let pos = self.try_get_mouse().and_then(|x| x.try_loc());
+        let (finite, in_outputs) = match &pos {
+            Ok(pos) if !pos.0.is_finite() || !pos.1.is_finite() => (
+                Check::Failed(format!("{pos:?} is not finite")),
+                Check::Skipped("position is not finite".into()),
+            ),
+            Ok(_) if outputs.is_empty() => {
+                (Check::Passed, Check::Skipped("no output is given".into()))
+            }
+            Ok(pos) if outputs.iter().any(|x| x.contains(*pos)) => (Check::Passed, Check::Passed),
+            Ok(pos) => (
+                Check::Passed,
+                Check::Failed(format!("{pos:?} is outside of all outputs")),
+            ),
+            Err(e) => (Check::Failed(e.to_string()), unreadable(e)),
+        };
+        Validation {
+            object: Some(object),
+            pos: pos.ok(),
+            heap,
+            finite,
+            outputs: in_outputs,
+            movement: Check::Skipped(String::new()),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+// included in `pointer`.
+
+/// a position change reported by `Mouse::watch`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    /// monotonic time when the position is read.
+    pub time: std::time::Instant,
+    /// the new position.
+    pub pos: (f64, f64),
+    /// change since the previous sample (or since the watch started).
+    pub delta: (f64, f64),
+    /// `delta` divided by the elapsed seconds, in pixels per second.
+    pub velocity: (f64, f64),
+}
+
+/// options of `Mouse::watch_with`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WatchOptions {
+    interval: std::time::Duration,
+    epsilon: f64,
+    min_gap: std::time::Duration,
+    idle_timeout: Option<std::time::Duration>,
+}
+impl WatchOptions {
+    /// poll every `interval`, report any change.
+    pub fn new(interval: std::time::Duration) -> Self {
+        Self {
+            interval,
+            epsilon: 0.0,
+            min_gap: std::time::Duration::ZERO,
+            idle_timeout: None,
+        }
+    }
+    /// only report when the position moves farther than `epsilon` (euclidean distance) from the last sample.
+    pub fn epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+    /// report at most `hz` samples per second, changes in between are merged into the next sample.
+    ///
+    /// A rate that is not positive (or NaN) removes the limit.
+    pub fn max_rate(mut self, hz: f64) -> Self {
+        self.min_gap = match hz > 0.0 {
+            true => std::time::Duration::try_from_secs_f64(hz.recip())
+                .unwrap_or(std::time::Duration::MAX),
+            false => std::time::Duration::ZERO,
+        };
+        self
+    }
+    /// stop watching after the position does not change for `timeout`.
+    pub fn idle_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+}
+
+/// iterator returned by `Mouse::watch`, it blocks the current thread between polls.
+///
+/// It ends after the idle timeout, or after yielding the first error.
+pub struct Watch<'a> {
+    mouse: &'a Mouse,
+    options: WatchOptions,
+    last: (std::time::Instant, (f64, f64)),
+    // the previous poll, which is not always reported.
+    polled: (f64, f64),
+    error: Option<Error>,
+    done: bool,
+    last_change: std::time::Instant,
+    next_poll: std::time::Instant,
+}
+impl Iterator for Watch<'_> {
+    type Item = Result<Sample, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if let Some(e) = self.error.take() {
+            self.done = true;
+            return Some(Err(e));
+        }
+        let (last_time, last_pos) = self.last;
+        loop {
+            let now = std::time::Instant::now();
+            if let Some(timeout) = self.options.idle_timeout
+                && now.duration_since(self.last_change) >= timeout
+            {
+                self.done = true;
+                return None;
+            }
+            if self.next_poll > now {
+                std::thread::sleep(self.next_poll - now);
+            }
+            self.next_poll = self.next_poll.max(now) + self.options.interval;
+            let pos = match self.mouse.try_loc() {
+                Ok(x) => x,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+            let time = std::time::Instant::now();
+            let delta = (pos.0 - last_pos.0, pos.1 - last_pos.1);
+            if pos != self.polled {
+                self.last_change = time;
+                self.polled = pos;
+            }
+            if delta.0.hypot(delta.1) <= self.options.epsilon
+                || time.duration_since(last_time) < self.options.min_gap
+            {
+                continue;
+            }
+            let secs = time.duration_since(last_time).as_secs_f64();
+            self.last = (time, pos);
+            return Some(Ok(Sample {
+                time,
+                pos,
+                delta,
+                velocity: (delta.0 / secs, delta.1 / secs),
+            }));
+        }
+    }
+}
+impl std::iter::FusedIterator for Watch<'_> {}
+
+impl Mouse {
+    /// poll the position every `interval`, and yield only when it changes.
+    ///
+    /// ```no_run
+    /// use kwin_mouse_loc::pointer::Workspace;
+    /// use std::time::Duration;
+    /// let mouse = unsafe { Workspace::new(true) }.get_mouse();
+    /// for sample in mouse.watch(Duration::from_millis(10)).take(10) {
+    ///     let sample = sample.unwrap();
+    ///     println!("{:?} moved {:?} at {:?} px/s", sample.pos, sample.delta, sample.velocity);
+    /// }
+    /// ```
+    pub fn watch(&self, interval: std::time::Duration) -> Watch<'_> {
+        self.watch_with(WatchOptions::new(interval))
+    }
+    /// like `watch`, with epsilon, max rate and idle timeout.
+    pub fn watch_with(&self, options: WatchOptions) -> Watch<'_> {
+        let now = std::time::Instant::now();
+        let (pos, error) = match self.try_loc() {
+            Ok(pos) => (pos, None),
+            Err(e) => ((f64::NAN, f64::NAN), Some(e)),
+        };
+        Watch {
+            mouse: self,
+            options,
+            last: (now, pos),
+            polled: pos,
+            error,
+            done: false,
+            last_change: now,
+            next_poll: now + options.interval,
+        }
+    }
+    /// callback version of `watch_with`, `f` returns `false` to stop watching.
+    ///
+    /// Returns after the idle timeout, after `f` returns `false`, or on the first error.
+    pub fn watch_callback(
+        &self,
+        options: WatchOptions,
+        mut f: impl FnMut(Sample) -> bool,
+    ) -> Result<(), Error> {
+        for sample in self.watch_with(options) {
+            if !f(sample?) {
+                break;
+            }
+        }
+        Ok(())
+    }
+}